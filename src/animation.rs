@@ -1,62 +1,349 @@
+use std::collections::HashMap;
+use std::mem;
 use sfml::graphics::rc::Sprite;
-use sfml::system::Vector2f;
 use sfml::graphics::IntRect;
+use gfx::AnimSetOptions;
+use util::get_sprite_coords;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoopMode {
+	/// back to the first frame after the last
+	Loop,
+	/// stop on the last frame
+	Once,
+	/// run forward, then backward, without repeating the end frames
+	PingPong,
+}
+
+impl LoopMode {
+	pub fn from_name(name: &str) -> Option<LoopMode> {
+		match name {
+			"loop" => Some(LoopMode::Loop),
+			"once" => Some(LoopMode::Once),
+			"ping_pong" => Some(LoopMode::PingPong),
+			_ => None
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Clip {
+	pub frames: Vec<IntRect>,
+	/// seconds each frame is shown
+	pub durations: Vec<f32>,
+	pub mode: LoopMode,
+	/// (frame, event name) pairs, fired when the frame comes up
+	pub events: Vec<(usize, String)>,
+}
+
+impl Clip {
+	/// time to play every frame once
+	pub fn length(&self) -> f32 {
+		self.durations.iter().fold(0.0, |sum, &d| sum + d)
+	}
+}
 
 #[derive(Clone)]
 pub struct Animation {
 	pub sprite: Sprite,
-	frame_set: usize,
-	pub frame_sets: Vec<Vec<IntRect>>,
+	clips: HashMap<String, Clip>,
+	// state -> facing -> clip
+	states: HashMap<String, HashMap<String, String>>,
+	clip: String,
 	pub timer: f32,
-	pub length: f32,
 	frame: usize,
+	// ping-pong clips on their way back
+	reverse: bool,
+	finished: bool,
+	// events fired since the last update
+	fired: Vec<(String, usize)>,
 }
 
-// impl Clone for Animation {
-// 	fn clone(&self) -> Animation {
-// 		Animation {
-// 			sprite: self.sprite.clone().expect("Anim sprite clone failed"),
-// 			frame_set: self.frame_set,
-// 			frame_sets: self.frame_sets.clone(),
-// 			timer: self.timer,
-// 			length: self.length,
-// 			frame: self.frame,
-// 		}
-// 	}
-// }
-
 impl Animation {
 	/* public */
-	pub fn new(sprite: &Sprite, frames: &Vec<IntRect>, length: f32) -> Animation {
-		Animation {
+	pub fn new(sprite: &Sprite, opts: &AnimSetOptions) -> Animation {
+		let (tw, th) = (opts.tile.0 as usize, opts.tile.1 as usize);
+		let clips = opts.clips.iter().map(|(name, c)| {
+			let frames: Vec<IntRect> = c.frames.iter().map(|&(x, y)| {
+				let (px, py) = get_sprite_coords(x as usize, y as usize, tw, th);
+				IntRect{ left: px as i32, top: py as i32, width: tw as i32, height: th as i32 }
+			}).collect();
+			let mut events: Vec<(usize, String)> = c.events.iter()
+				.flat_map(|events| events.iter())
+				.flat_map(|(name, frames)| frames.iter().map(move |&f| (f, name.clone())))
+				.collect();
+			events.sort();
+			let clip = Clip {
+				durations: (0..frames.len()).map(|i| c.duration(i)).collect(),
+				frames: frames,
+				mode: LoopMode::from_name(c.mode()).unwrap_or(LoopMode::Loop),
+				events: events,
+			};
+			(name.clone(), clip)
+		}).collect();
+
+		let mut anim = Animation {
 			sprite: sprite.clone(),
-			frame_sets: vec!(frames.clone()),
-			frame_set: 0,
+			clips: clips,
+			states: opts.state_clips(),
+			clip: opts.default_clip().expect("AnimSetOptions weren't validated").to_string(),
 			timer: 0.0,
-			length: length,
 			frame: 0,
-		}
+			reverse: false,
+			finished: false,
+			fired: Vec::new(),
+		};
+		anim.update_rect();
+		anim
 	}
-	pub fn update(&mut self, delta: f32) {
+	/// Advances the current clip, returning the (event, frame) pairs for
+	/// every frame it reached.
+	pub fn update(&mut self, delta: f32) -> Vec<(String, usize)> {
+		if self.finished {
+			return mem::replace(&mut self.fired, Vec::new());
+		}
 		self.timer += delta;
-		let frame_count = self.frame_sets[self.frame_set].len();
-		let length = self.length / frame_count as f32;
-		while self.timer >= length {
-			self.timer -= length;
-			self.frame = (self.frame + 1) % frame_count;
+		loop {
+			let duration = self.current().durations[self.frame];
+			if self.timer < duration {
+				break;
+			}
+			self.timer -= duration;
+			if !self.advance() {
+				self.timer = 0.0;
+				break;
+			}
+			self.fire_events();
+		}
+		self.update_rect();
+		mem::replace(&mut self.fired, Vec::new())
+	}
+	/// Switches to the named clip from its first frame. Asking for the clip
+	/// that's already playing does nothing unless it has finished.
+	pub fn play(&mut self, name: &str) -> bool {
+		if !self.clips.contains_key(name) {
+			return false;
+		}
+		if self.clip != name || self.finished {
+			self.clip = name.to_string();
+			self.timer = 0.0;
+			self.frame = 0;
+			self.reverse = false;
+			self.finished = false;
+			self.fire_events();
 			self.update_rect();
 		}
+		true
 	}
-	pub fn set_frame_set(&mut self, idx: usize) {
-		self.frame_set = idx;
-		self.update_rect();
+	/// Clip for a state while facing some way, falling back on the state's
+	/// `any` clip.
+	pub fn clip_for(&self, state: &str, facing: &str) -> Option<String> {
+		self.states.get(state)
+			.and_then(|f| f.get(facing).or(f.get("any")))
+			.cloned()
+	}
+	pub fn has_state(&self, state: &str) -> bool {
+		self.states.contains_key(state)
+	}
+	pub fn clip_name(&self) -> &str {
+		&self.clip
+	}
+	/// length of the current clip
+	pub fn length(&self) -> f32 {
+		self.current().length()
+	}
+	/// true once a `once` clip has shown its last frame
+	pub fn is_finished(&self) -> bool {
+		self.finished
 	}
 	/* private */
+	fn current(&self) -> &Clip {
+		&self.clips[&self.clip]
+	}
+	// steps to the next frame; false when a `once` clip runs out
+	fn advance(&mut self) -> bool {
+		let (count, mode) = {
+			let clip = self.current();
+			(clip.frames.len(), clip.mode)
+		};
+		match mode {
+			LoopMode::Loop => self.frame = (self.frame + 1) % count,
+			LoopMode::Once => {
+				if self.frame + 1 >= count {
+					self.finished = true;
+					return false;
+				}
+				self.frame += 1;
+			}
+			LoopMode::PingPong if count > 1 => {
+				if self.frame == 0 {
+					self.reverse = false;
+				} else if self.frame + 1 == count {
+					self.reverse = true;
+				}
+				if self.reverse { self.frame -= 1; } else { self.frame += 1; }
+			}
+			LoopMode::PingPong => {}
+		}
+		true
+	}
+	fn fire_events(&mut self) {
+		let frame = self.frame;
+		let fired: Vec<(String, usize)> = self.current().events.iter()
+			.filter(|&&(f, _)| f == frame)
+			.map(|&(f, ref name)| (name.clone(), f))
+			.collect();
+		self.fired.extend(fired);
+	}
 	fn update_rect(&mut self) {
-		self.sprite.set_texture_rect(
-			&self.frame_sets[self.frame_set][
-				self.frame
-			]
-		);
+		let rect = self.current().frames[self.frame];
+		self.sprite.set_texture_rect(&rect);
 	}
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum AnimState {
+	Idle,
+	Walk,
+	Attack,
+	Hurt,
+	Death,
+}
+
+impl AnimState {
+	/// key used for this state in animation files
+	pub fn name(&self) -> &'static str {
+		match *self {
+			AnimState::Idle => "idle",
+			AnimState::Walk => "walk",
+			AnimState::Attack => "attack",
+			AnimState::Hurt => "hurt",
+			AnimState::Death => "death",
+		}
+	}
+	/// Idle and walk loop until told otherwise; the rest play out once.
+	pub fn is_action(&self) -> bool {
+		match *self {
+			AnimState::Idle | AnimState::Walk => false,
+			_ => true
+		}
+	}
+	// which actions can cut each other off
+	fn priority(&self) -> u8 {
+		match *self {
+			AnimState::Idle | AnimState::Walk => 0,
+			AnimState::Attack => 1,
+			AnimState::Hurt => 2,
+			AnimState::Death => 3,
+		}
+	}
+}
+
+/// A frame event, e.g. `hit` on an attack clip's third frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimEvent {
+	pub name: String,
+	pub state: AnimState,
+	pub frame: usize,
+}
+
+/// Picks clips for a creature from its state and facing.
+///
+/// Switching is immediate: the new clip starts from its first frame.
+/// Attack and hurt play to the end and then drop back to idle unless
+/// something more important (hurt, then death) cuts them off. Death
+/// is final. Idle and death hold the current frame if the animation
+/// file has no clips for them; attack and hurt are refused instead.
+#[derive(Clone)]
+pub struct AnimController {
+	anim: Animation,
+	state: AnimState,
+	facing: &'static str,
+	events: Vec<AnimEvent>,
+}
+
+impl AnimController {
+	pub fn new(anim: &Animation) -> AnimController {
+		let mut ctrl = AnimController {
+			anim: anim.clone(),
+			state: AnimState::Idle,
+			facing: "south",
+			events: Vec::new(),
+		};
+		ctrl.play_current();
+		ctrl
+	}
+	pub fn sprite(&self) -> &Sprite {
+		&self.anim.sprite
+	}
+	pub fn sprite_mut(&mut self) -> &mut Sprite {
+		&mut self.anim.sprite
+	}
+	pub fn state(&self) -> AnimState {
+		self.state
+	}
+	/// Asks for a new state; false if the current one can't be cut off
+	/// or the new one has no clips.
+	pub fn set_state(&mut self, state: AnimState) -> bool {
+		if state == self.state {
+			return true;
+		}
+		if self.state == AnimState::Death {
+			return false;
+		}
+		if self.state.is_action() && !self.anim.is_finished() &&
+			state.priority() <= self.state.priority() {
+			return false;
+		}
+		let holds = state == AnimState::Idle || state == AnimState::Death;
+		if !holds && !self.anim.has_state(state.name()) {
+			return false;
+		}
+		self.state = state;
+		self.play_current();
+		true
+	}
+	/// Turns to face a direction (one of `gfx::FACINGS`). Actions already
+	/// playing finish facing the way they started.
+	pub fn set_facing(&mut self, facing: &'static str) {
+		if facing == self.facing {
+			return;
+		}
+		self.facing = facing;
+		if !self.state.is_action() {
+			self.play_current();
+		}
+	}
+	pub fn update(&mut self, delta: f32) {
+		if !self.anim.has_state(self.state.name()) {
+			return;
+		}
+		let state = self.state;
+		for (name, frame) in self.anim.update(delta) {
+			self.events.push(AnimEvent { name: name, state: state, frame: frame });
+		}
+		if self.state.is_action() && self.state != AnimState::Death && self.anim.is_finished() {
+			self.state = AnimState::Idle;
+			self.play_current();
+		}
+	}
+	/// Events fired since the last call.
+	pub fn take_events(&mut self) -> Vec<AnimEvent> {
+		mem::replace(&mut self.events, Vec::new())
+	}
+	/// true once the death clip (if any) has played out
+	pub fn is_done_dying(&self) -> bool {
+		self.state == AnimState::Death &&
+			(self.anim.is_finished() || !self.anim.has_state("death"))
+	}
+	fn play_current(&mut self) {
+		if let Some(clip) = self.anim.clip_for(self.state.name(), self.facing) {
+			self.anim.play(&clip);
+			// events on the first frame fire as the clip starts
+			let state = self.state;
+			for (name, frame) in self.anim.update(0.0) {
+				self.events.push(AnimEvent { name: name, state: state, frame: frame });
+			}
+		}
+	}
+}
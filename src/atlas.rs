@@ -0,0 +1,30 @@
+// sprite positions in res/gfx/all_tiles.png, measured in tiles
+
+pub static ATLAS_FILE: &'static str = "all_tiles.png";
+pub const TILE_SIZE: usize = 16;
+pub const ATLAS_SIZE: (usize, usize) = (256, 256);
+pub const ATLAS_COLUMNS: usize = 16; // ATLAS_SIZE.0 / TILE_SIZE
+
+pub type SpritePos = (usize, usize);
+
+// dungeon tiles are defined in res/gfx/tiles/ -- see gfx::SpriteRegistry
+
+/* creatures -- first frame of each walk cycle */
+pub const HERO: SpritePos = (4, 8);
+pub const MONSTERS: [SpritePos; 4] = [
+    (10, 8),
+    (10, 12),
+    (7, 12),
+    (4, 12),
+    // (1, 12), // slime
+];
+
+/// Index of a sprite when the atlas is read left to right, top to bottom.
+pub fn sprite_index(pos: SpritePos) -> usize {
+    let (x, y) = pos;
+    x + y * ATLAS_COLUMNS
+}
+
+pub fn sprite_pos(index: usize) -> SpritePos {
+    (index % ATLAS_COLUMNS, index / ATLAS_COLUMNS)
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use rustc_serialize::json;
+
+use sfml::audio::SoundBuffer;
+use sfml::audio::rc::Sound;
+use sfml::audio::Music;
+use sfml::audio;
+
+use util::{self, get_snd_path, get_rc_resource};
+
+/// sound definitions, in res/snd/
+pub static SOUNDS_FILE: &'static str = "sounds.json";
+/// most sounds playing at once when the definitions don't say
+pub static DEFAULT_VOICES: usize = 16;
+/// tiles a sound carries when its definition doesn't say
+pub static DEFAULT_RANGE: f32 = 10.0;
+
+#[derive(RustcDecodable, RustcEncodable, Clone, Debug)]
+pub struct SoundDef {
+	/// relative to res/snd/
+	pub file: String,
+	/// 0 to 100, defaults to 100
+	pub volume: Option<f32>,
+	/// tiles from the hero at which it fades out completely
+	pub range: Option<f32>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Clone, Debug, Default)]
+pub struct SoundDefs {
+	pub sounds: HashMap<String, SoundDef>,
+	/// floor number (or `default`) to a music file in res/snd/
+	pub music: HashMap<String, String>,
+	/// most sounds playing at once; defaults to 16
+	pub voices: Option<usize>,
+	pub music_volume: Option<f32>,
+}
+
+/// Reads a sound definition file.
+pub fn load_sound_defs(path: &str) -> Result<SoundDefs, String> {
+	let mut s = String::new();
+	try!(File::open(path).and_then(|mut f| f.read_to_string(&mut s))
+			.map_err(|e| format!("{}: {}", path, e)));
+	let defs: SoundDefs = try!(json::decode(&s).map_err(|e| format!("{}: {}", path, e)));
+	for (name, def) in defs.sounds.iter() {
+		match def.range {
+			Some(range) if !(range > 0.0) =>
+				return Err(format!("{}: sound `{}` has range {}, should be more than 0", path, name, range)),
+			_ => {}
+		}
+	}
+	Ok(defs)
+}
+
+/// Whatever actually makes noise. Paths are full paths to files.
+pub trait AudioBackend {
+	/// Loads and caches a sound; false if it can't be read.
+	fn load(&mut self, path: &str) -> bool;
+	/// Starts a loaded sound at a volume from 0 to 100.
+	fn play(&mut self, path: &str, volume: f32);
+	/// Sounds still playing.
+	fn voices(&mut self) -> usize;
+	fn play_music(&mut self, path: &str, volume: f32) -> bool;
+	fn stop_music(&mut self);
+}
+
+/// Plays through SFML.
+pub struct SfmlBackend {
+	buffers: HashMap<String, Rc<RefCell<SoundBuffer>>>,
+	sounds: Vec<Sound>,
+	music: Option<Music>,
+}
+
+impl SfmlBackend {
+	pub fn new() -> SfmlBackend {
+		SfmlBackend {
+			buffers: HashMap::new(),
+			sounds: Vec::new(),
+			music: None,
+		}
+	}
+}
+
+impl AudioBackend for SfmlBackend {
+	fn load(&mut self, path: &str) -> bool {
+		if self.buffers.contains_key(path) {
+			return true;
+		}
+		match SoundBuffer::new(path) {
+			Some(buf) => {
+				self.buffers.insert(path.to_string(), get_rc_resource(buf));
+				true
+			}
+			None => false
+		}
+	}
+
+	fn play(&mut self, path: &str, volume: f32) {
+		let buf = match self.buffers.get(path) {
+			Some(buf) => buf.clone(),
+			None => return
+		};
+		if let Some(mut sound) = Sound::new_with_buffer(buf) {
+			sound.set_volume(volume);
+			sound.play();
+			self.sounds.push(sound);
+		}
+	}
+
+	fn voices(&mut self) -> usize {
+		// clean up sounds that have finished
+		self.sounds.retain(|s| match s.get_status() {
+			audio::Stopped => false,
+			_ => true
+		});
+		self.sounds.len()
+	}
+
+	fn play_music(&mut self, path: &str, volume: f32) -> bool {
+		match Music::new_from_file(path) {
+			Some(mut song) => {
+				song.set_loop(true);
+				song.set_volume(volume);
+				song.play();
+				self.music = Some(song);
+				true
+			}
+			None => false
+		}
+	}
+
+	fn stop_music(&mut self) {
+		self.music = None;
+	}
+}
+
+/// Makes no noise, for headless runs. Sounds finish as soon as they start.
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+	fn load(&mut self, _: &str) -> bool { true }
+	fn play(&mut self, _: &str, _: f32) {}
+	fn voices(&mut self) -> usize { 0 }
+	fn play_music(&mut self, _: &str, _: f32) -> bool { true }
+	fn stop_music(&mut self) {}
+}
+
+/// Named sounds, quieter the further they are from the listener.
+pub struct SoundManager {
+	defs: SoundDefs,
+	backend: Box<AudioBackend>,
+	/// where the hero is, in tiles
+	listener: (f32, f32),
+	music: Option<String>,
+	// files we've already complained about
+	missing: HashSet<String>,
+}
+
+impl SoundManager {
+	pub fn new(defs: SoundDefs, backend: Box<AudioBackend>) -> SoundManager {
+		let mut manager = SoundManager {
+			defs: defs,
+			backend: backend,
+			listener: (0.0, 0.0),
+			music: None,
+			missing: HashSet::new(),
+		};
+		// load everything up front so the first footstep doesn't stall
+		let files: Vec<String> = manager.defs.sounds.values().map(|d| d.file.clone()).collect();
+		for file in files.iter() {
+			manager.load(file);
+		}
+		manager
+	}
+
+	/// res/snd/sounds.json through SFML; silent (with a warning) if the
+	/// definitions can't be read.
+	pub fn load_default() -> SoundManager {
+		match load_sound_defs(&get_snd_path(SOUNDS_FILE)) {
+			Ok(defs) => SoundManager::new(defs, Box::new(SfmlBackend::new())),
+			Err(e) => {
+				println!("Warning: no sounds ({})", e);
+				SoundManager::null()
+			}
+		}
+	}
+
+	pub fn null() -> SoundManager {
+		SoundManager::new(SoundDefs::default(), Box::new(NullBackend))
+	}
+
+	pub fn set_listener(&mut self, x: f32, y: f32) {
+		self.listener = (x, y);
+	}
+
+	/// Volume a sound would play at from (x,y) in tiles, or None if it's
+	/// undefined or out of earshot.
+	pub fn volume_at(&self, name: &str, x: f32, y: f32) -> Option<f32> {
+		let def = match self.defs.sounds.get(name) {
+			Some(def) => def,
+			None => return None
+		};
+		let (lx, ly) = self.listener;
+		let dist = ((x - lx)*(x - lx) + (y - ly)*(y - ly)).sqrt();
+		let range = def.range.unwrap_or(DEFAULT_RANGE);
+		let falloff = util::clamp(1.0 - dist / range, 0.0, 1.0);
+		let volume = def.volume.unwrap_or(100.0) * falloff;
+		if volume > 0.0 { Some(volume) } else { None }
+	}
+
+	/// Plays a sound heard from (x,y), in tiles. Returns the volume it
+	/// played at; None if it's out of earshot or every voice is busy.
+	pub fn play_at(&mut self, name: &str, x: f32, y: f32) -> Option<f32> {
+		let volume = match self.volume_at(name, x, y) {
+			Some(volume) => volume,
+			None => return None
+		};
+		if self.backend.voices() >= self.defs.voices.unwrap_or(DEFAULT_VOICES) {
+			return None;
+		}
+		let file = self.defs.sounds[name].file.clone();
+		if !self.load(&file) {
+			return None;
+		}
+		self.backend.play(&get_snd_path(&file), volume);
+		Some(volume)
+	}
+
+	/// Plays a sound at the listener, at full volume.
+	pub fn play(&mut self, name: &str) -> Option<f32> {
+		let (x, y) = self.listener;
+		self.play_at(name, x, y)
+	}
+
+	/// Starts the floor's music (or the default track), unless it's
+	/// already playing.
+	pub fn play_floor_music(&mut self, floor: u32) {
+		let file = self.defs.music.get(&floor.to_string())
+			.or(self.defs.music.get("default"))
+			.cloned();
+		if file == self.music {
+			return;
+		}
+		self.backend.stop_music();
+		self.music = None;
+		if let Some(file) = file {
+			let volume = self.defs.music_volume.unwrap_or(100.0);
+			if self.backend.play_music(&get_snd_path(&file), volume) {
+				self.music = Some(file);
+			} else if self.missing.insert(file.clone()) {
+				println!("Warning: couldn't play music {}", get_snd_path(&file));
+			}
+		}
+	}
+
+	pub fn has_music(&self) -> bool {
+		self.music.is_some()
+	}
+
+	pub fn voices(&mut self) -> usize {
+		self.backend.voices()
+	}
+
+	/// Lets go of finished sounds; call once a frame.
+	pub fn update(&mut self) {
+		self.backend.voices();
+	}
+
+	// loads a sound file, warning once if it's missing
+	fn load(&mut self, file: &str) -> bool {
+		if self.missing.contains(file) {
+			return false;
+		}
+		if self.backend.load(&get_snd_path(file)) {
+			return true;
+		}
+		println!("Warning: couldn't load sound {}", get_snd_path(file));
+		self.missing.insert(file.to_string());
+		false
+	}
+}
@@ -0,0 +1,154 @@
+use std::ops::Range;
+use std::u32;
+
+use docopt::Docopt;
+
+use export::Format;
+use seed::Seed;
+
+pub const USAGE: &'static str = "
+Rusty Rogue
+
+Usage:
+  rusty-rogue [play] [--seed=<seed> | --map=<file>] [--params=<file>] [--window=<size>] [--tick-rate=<hz>] [--config=<file>] [--new]
+  rusty-rogue gen [--count=<n>] [--seed=<seed>] [--format=<format>] [--params=<file>]
+  rusty-rogue validate [--count=<n>] [--seed=<seed>] [--params=<file>]
+  rusty-rogue stats [--count=<n>] [--seed=<seed>] [--params=<file>] [--json-report]
+  rusty-rogue golden [--update]
+  rusty-rogue search-bench
+  rusty-rogue test <name>
+  rusty-rogue (-h | --help)
+
+Commands:
+  play          Play the game (the default). Opens on the title menu unless
+                given a seed or map.
+  gen           Generate dungeons and write them out.
+  validate      Check generated dungeons for unreachable rooms and the like.
+  stats         Collect generator statistics, one CSV row per seed.
+  golden        Check that known seeds still generate the same dungeons.
+  search-bench  Time pathfinding across a random obstacle field.
+  test          Run one of the other checks: gen, dungeon, json, tiled,
+                sound, input, config, anim, timestep or
+                autotile.
+
+Options:
+  --seed=<seed>      For play, a seed code to replay, skipping the menu. For
+                     the others, the first of a run of numbered seeds; gen
+                     and validate also take a seed code, on its own.
+  --map=<file>       Play a Tiled map as a fixed level.
+  --params=<file>    Dungeon generation parameters, as JSON. Anything left
+                     out keeps its default.
+  --window=<size>    Window size, e.g. 1024x768. Overrides the config file.
+  --config=<file>    Settings file to use instead of ./config.json.
+  --tick-rate=<hz>   Game updates per second.
+  --new              Play the new Piston-powered version (super incomplete).
+  --count=<n>        How many seeds to go through.
+  --format=<format>  ascii (printed), png (to renders/) or json (Tiled maps,
+                     to res/maps/) [default: ascii].
+  --json-report      Print stats as one JSON report instead of CSV.
+  --update           Record the current output as the new golden master.
+  -h, --help         Show this message.
+";
+
+/// Everything docopt understood, named its way.
+#[derive(RustcDecodable, Debug)]
+pub struct Args {
+    pub cmd_play: bool,
+    pub cmd_gen: bool,
+    pub cmd_validate: bool,
+    pub cmd_stats: bool,
+    pub cmd_golden: bool,
+    pub cmd_search_bench: bool,
+    pub cmd_test: bool,
+    pub arg_name: Option<String>,
+    pub flag_seed: Option<String>,
+    pub flag_map: Option<String>,
+    pub flag_params: Option<String>,
+    pub flag_window: Option<String>,
+    pub flag_tick_rate: Option<f64>,
+    pub flag_config: Option<String>,
+    pub flag_new: bool,
+    pub flag_count: Option<u32>,
+    pub flag_format: Format,
+    pub flag_json_report: bool,
+    pub flag_update: bool,
+}
+
+/// Parses the command line, or prints usage and exits: with 0 for `--help`,
+/// 1 for anything it doesn't understand.
+pub fn parse() -> Args {
+    Docopt::new(USAGE)
+        .and_then(|d| d.decode())
+        .unwrap_or_else(|e| e.exit())
+}
+
+/// What gen and validate go through: a run of numbered seeds, or the one
+/// seed a code names.
+#[derive(Clone, Debug)]
+pub enum Seeds {
+    Numbered(Range<u32>),
+    Code(Seed),
+}
+
+impl Seeds {
+    pub fn len(&self) -> u32 {
+        match *self {
+            Seeds::Numbered(ref range) => range.end - range.start,
+            Seeds::Code(_) => 1,
+        }
+    }
+
+    pub fn iter(&self) -> Box<Iterator<Item = Seed>> {
+        match *self {
+            Seeds::Numbered(ref range) => Box::new(range.clone().map(Seed::from)),
+            Seeds::Code(ref seed) => Box::new(Some(seed.clone()).into_iter()),
+        }
+    }
+}
+
+/// `--seed` for the commands that count up from a numbered seed.
+pub fn first_seed(args: &Args) -> Result<u32, String> {
+    match args.flag_seed {
+        None => Ok(0),
+        Some(ref seed) => seed.parse::<u32>()
+            .map_err(|_| format!("--seed must be a number here, got `{}`", seed)),
+    }
+}
+
+/// `--seed` and `--count` for gen and validate: a seed code (like
+/// `MOSSY-RAVEN-17`) for just that dungeon, or else `seed_range`.
+pub fn seeds(args: &Args, default_count: u32) -> Result<Seeds, String> {
+    match args.flag_seed {
+        Some(ref code) if code.parse::<u32>().is_err() => match args.flag_count {
+            None | Some(1) => Ok(Seeds::Code(Seed::new(code))),
+            Some(count) => Err(format!("--seed `{}` is a seed code, so --count can only be 1, got {} \
+                    (use a number to go through a run of seeds)", code, count)),
+        },
+        _ => seed_range(args, default_count).map(Seeds::Numbered),
+    }
+}
+
+/// The numbered seeds from `--seed` on, `--count` of them (or
+/// `default_count`). They all have to fit in a u32.
+pub fn seed_range(args: &Args, default_count: u32) -> Result<Range<u32>, String> {
+    let first = try!(first_seed(args));
+    let count = args.flag_count.unwrap_or(default_count);
+    match first.checked_add(count) {
+        Some(end) => Ok(first..end),
+        None => Err(format!("--seed {} and --count {} run past the last seed, {}",
+                first, count, u32::MAX)),
+    }
+}
+
+/// `WIDTHxHEIGHT`, e.g. `1024x768`.
+pub fn parse_window_size(size: &str) -> Result<(u32, u32), String> {
+    let bad = || format!("--window should look like 800x600, got `{}`", size);
+    let parts: Vec<&str> = size.split('x').collect();
+    if parts.len() != 2 {
+        return Err(bad());
+    }
+    match (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(bad()),
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, Json};
+
+/// user settings, all optional
+pub static CONFIG_FILE: &'static str = "./config.json";
+
+/// Window, graphics and gameplay settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+	/// width and height of the game window
+	pub window: (u32, u32),
+	/// the same, for the Piston-powered version
+	pub new_window: (u32, u32),
+	/// magnifications zooming in and out steps through, smallest first
+	pub zoom_levels: Vec<f32>,
+	/// threads finding paths for monsters
+	pub solver_threads: usize,
+	/// tiles per second
+	pub hero_speed: f32,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			window: (800, 600),
+			new_window: (800, 800),
+			zoom_levels: vec![1., 2., 3., 4.],
+			solver_threads: 4,
+			hero_speed: 4.0,
+		}
+	}
+}
+
+impl Config {
+	/// Reads a config file over the defaults, printing a warning for
+	/// anything that's missing or wrong. There doesn't have to be a file.
+	pub fn load(path: &str) -> Config {
+		let (config, warnings) = Config::load_quietly(path);
+		for warning in warnings.iter() {
+			println!("Warning: {}", warning);
+		}
+		config
+	}
+
+	/// Same as `load`, but hands back the warnings instead of printing them.
+	pub fn load_quietly(path: &str) -> (Config, Vec<String>) {
+		let mut s = String::new();
+		match File::open(path).and_then(|mut f| f.read_to_string(&mut s)) {
+			Ok(_) => Config::parse(path, &s),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => (Config::default(), Vec::new()),
+			Err(e) => (Config::default(), vec![format!("{}: {}; using the defaults", path, e)]),
+		}
+	}
+
+	/// Reads config JSON; `path` is only for the warnings.
+	pub fn parse(path: &str, text: &str) -> (Config, Vec<String>) {
+		let mut config = Config::default();
+		let obj = match Json::from_str(text) {
+			Ok(Json::Object(obj)) => obj,
+			Ok(_) => return (config, vec![format!("{}: expected an object; using the defaults", path)]),
+			Err(e) => return (config, vec![format!("{}: {}; using the defaults", path, e)]),
+		};
+		let mut r = Reader { path: path, obj: obj, warnings: Vec::new(), missing: Vec::new() };
+
+		if let Some(size) = r.get("window", "a width and height above 0, like [800, 600]",
+				|&(w, h): &(u32, u32)| w > 0 && h > 0) {
+			config.window = size;
+		}
+		if let Some(size) = r.get("new_window", "a width and height above 0, like [800, 800]",
+				|&(w, h): &(u32, u32)| w > 0 && h > 0) {
+			config.new_window = size;
+		}
+		if let Some(levels) = r.get("zoom_levels", "a list of magnifications above 0, like [1, 2, 3, 4]",
+				|levels: &Vec<f32>| !levels.is_empty() && levels.iter().all(|&z| z > 0.0 && z.is_finite())) {
+			config.zoom_levels = levels;
+		}
+		if let Some(threads) = r.get("solver_threads", "between 1 and 64",
+				|&threads: &usize| threads > 0 && threads <= 64) {
+			config.solver_threads = threads;
+		}
+		if let Some(speed) = r.get("hero_speed", "a speed above 0, in tiles per second",
+				|&speed: &f32| speed > 0.0 && speed.is_finite()) {
+			config.hero_speed = speed;
+		}
+
+		(config, r.finish())
+	}
+}
+
+// pulls keys out of a config object one at a time, noting what's wrong
+struct Reader<'a> {
+	path: &'a str,
+	obj: BTreeMap<String, Json>,
+	warnings: Vec<String>,
+	missing: Vec<&'static str>,
+}
+
+impl<'a> Reader<'a> {
+	fn get<T: Decodable, F: Fn(&T) -> bool>(&mut self, key: &'static str, expected: &str, valid: F)
+			-> Option<T> {
+		let value = match self.obj.remove(key) {
+			Some(value) => value,
+			None => {
+				self.missing.push(key);
+				return None;
+			}
+		};
+		if let Ok(v) = T::decode(&mut json::Decoder::new(value.clone())) {
+			if valid(&v) {
+				return Some(v);
+			}
+		}
+		self.warnings.push(format!("{}: `{}` should be {}, not {}; using the default",
+			self.path, key, expected, value));
+		None
+	}
+
+	// whatever's left over wasn't asked for
+	fn finish(mut self) -> Vec<String> {
+		for key in self.obj.keys() {
+			self.warnings.push(format!("{}: unknown setting `{}`", self.path, key));
+		}
+		if !self.missing.is_empty() {
+			self.warnings.push(format!("{}: no {} given; using the default(s)",
+				self.path, self.missing.join(", ")));
+		}
+		self.warnings
+	}
+}
@@ -1,7 +1,9 @@
 use sfml::system::Vector2f;
 use sfml::graphics::FloatRect;
+use sfml::graphics::Color;
 use sfml::graphics::{RenderTarget, RenderWindow};
-use animation::Animation;
+use animation::{Animation,AnimController,AnimState,AnimEvent};
+use search::CostProfile;
 use util::{self, AngleHelper};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -25,6 +27,15 @@ impl Facing {
 	pub fn from_rad(radians: f32) -> Facing {
 		Facing::from_deg(radians.to_deg())
 	}
+	/// key used for this facing in animation files
+	pub fn name(&self) -> &'static str {
+		match *self {
+			North => "north",
+			East => "east",
+			South => "south",
+			West => "west",
+		}
+	}
 }
 
 #[derive(Clone)]
@@ -32,13 +43,20 @@ pub struct Creature {
 	max_health: isize,
 	health: isize,
 	pos: Vector2f,
-	pub anim: Animation,
+	// where it was when the current tick started, for drawing in between
+	prev_pos: Vector2f,
+	pub anim: AnimController,
 	pub player: bool,
 	path: Vec<(isize,isize)>,
 	pub path_age: f32,
 	pub path_id: Option<usize>,
 	pub awake: bool,
 	pub path_target: Option<(isize,isize)>,
+	pub opens_doors: bool,
+	pub costs: CostProfile,
+	/// hazard tile last stood on, and time spent there
+	pub hazard_tile: Option<(isize,isize)>,
+	pub hazard_timer: f32,
 	facing: Facing,
 }
 
@@ -50,7 +68,8 @@ impl Creature {
 			max_health: max_health,
 			health: max_health,
 			pos: Vector2f::new(0.0,0.0),
-			anim: anim.clone(),
+			prev_pos: Vector2f::new(0.0,0.0),
+			anim: AnimController::new(anim),
 			player: false,
 			path: Vec::new(),
 			path_age: 0.0,
@@ -58,11 +77,15 @@ impl Creature {
 			awake: false,
 			facing: South,
 			path_target: None,
+			opens_doors: true,
+			costs: CostProfile::new(),
+			hazard_tile: None,
+			hazard_timer: 0.0,
 		};
 
 		// TODO better sprite origin calculation?
-		let bounds = c.anim.sprite.get_local_bounds();
-		c.anim.sprite.set_origin2f(bounds.width/2.0,bounds.height/2.0);
+		let bounds = c.anim.sprite().get_local_bounds();
+		c.anim.sprite_mut().set_origin2f(bounds.width/2.0,bounds.height/2.0);
 		c.update_anim_pos();
 
 		let f = c.facing;
@@ -71,9 +94,54 @@ impl Creature {
 		c
 	}
 
+	pub fn get_health(&self) -> isize {
+		self.health
+	}
+
+	pub fn damage(&mut self, amount: isize) {
+		self.health = util::clamp(self.health - amount, 0, self.max_health);
+		if self.health <= 0 {
+			self.anim.set_state(AnimState::Death);
+		} else if amount > 0 {
+			self.anim.set_state(AnimState::Hurt);
+		}
+	}
+
+	pub fn is_dead(&self) -> bool {
+		self.health <= 0
+	}
+
+	/// dead, and done showing it
+	pub fn is_gone(&self) -> bool {
+		self.is_dead() && self.anim.is_done_dying()
+	}
+
+	/// Starts an attack unless something's already playing; the hit
+	/// itself comes later as a `hit` event.
+	pub fn attack(&mut self) -> bool {
+		!self.is_dead() && self.anim.state() != AnimState::Attack &&
+			self.anim.set_state(AnimState::Attack)
+	}
+
+	pub fn is_attacking(&self) -> bool {
+		self.anim.state() == AnimState::Attack
+	}
+
+	/// Walking or standing around, unless busy with something else.
+	pub fn set_moving(&mut self, moving: bool) {
+		if !self.anim.state().is_action() {
+			self.anim.set_state(if moving { AnimState::Walk } else { AnimState::Idle });
+		}
+	}
+
+	/// Frame events (hits, footsteps) since the last call.
+	pub fn take_anim_events(&mut self) -> Vec<AnimEvent> {
+		self.anim.take_events()
+	}
+
 	pub fn set_facing(&mut self, facing: Facing) {
 		self.facing = facing;
-		self.anim.set_frame_set( facing as usize );
+		self.anim.set_facing( facing.name() );
 	}
 
 	pub fn set_facing_deg(&mut self, degrees: f32) {
@@ -86,10 +154,17 @@ impl Creature {
 
 	pub fn update_anim(&mut self, delta: f32) {
 		self.anim.update(delta);
+		// the atlas has no hurt or death frames, so tint instead
+		let tint = match self.anim.state() {
+			AnimState::Hurt => Color{ red: 255u8, green: 96u8, blue: 96u8, alpha: 255u8 },
+			AnimState::Death => Color{ red: 96u8, green: 96u8, blue: 96u8, alpha: 255u8 },
+			_ => Color::white()
+		};
+		self.anim.sprite_mut().set_color(&tint);
 	}
 
 	fn update_anim_pos(&mut self) {
-		self.anim.sprite.set_position2f( self.pos.x, self.pos.y );
+		self.anim.sprite_mut().set_position2f( self.pos.x, self.pos.y );
 	}
 
 	#[inline]
@@ -99,7 +174,7 @@ impl Creature {
 
 	// TODO make better
 	pub fn get_bounds_trimmed(&self, trim: f32) -> FloatRect {
-		let mut bounds = self.anim.sprite.get_global_bounds();
+		let mut bounds = self.anim.sprite().get_global_bounds();
 		let reduce_h = bounds.height / 2.0;
 		let reduce_w = bounds.width / 4.0;
 
@@ -129,16 +204,18 @@ impl Creature {
 	}
 
 	pub fn draw(&self, window: &mut RenderWindow) {
-		window.draw(&self.anim.sprite);
+		window.draw(self.anim.sprite());
 	}
 
 	pub fn set_scale2f(&mut self, x: f32, y: f32) {
-		self.anim.sprite.set_scale2f(x,y);
+		self.anim.sprite_mut().set_scale2f(x,y);
 	}
 
+	/// Puts the creature somewhere without it being seen to move there.
 	pub fn set_position2f(&mut self, x: f32, y: f32) {
 		self.pos.x = x;
 		self.pos.y = y;
+		self.prev_pos = self.pos;
 		self.update_anim_pos();
 	}
 
@@ -156,6 +233,26 @@ impl Creature {
 		self.pos
 	}
 
+	/// Call before each tick's movement. Puts the sprite back where the
+	/// creature really is, since bounds come from the sprite.
+	pub fn begin_tick(&mut self) {
+		self.prev_pos = self.pos;
+		self.update_anim_pos();
+	}
+
+	/// Where to draw it, `alpha` (0 to 1) of the way from where it was at
+	/// the start of the tick to where it is now.
+	pub fn interpolated_position(&self, alpha: f32) -> Vector2f {
+		Vector2f::new(self.prev_pos.x + (self.pos.x - self.prev_pos.x)*alpha,
+			self.prev_pos.y + (self.pos.y - self.prev_pos.y)*alpha)
+	}
+
+	/// Moves only the sprite; the next `begin_tick` puts it back.
+	pub fn interpolate(&mut self, alpha: f32) {
+		let pos = self.interpolated_position(alpha);
+		self.anim.sprite_mut().set_position2f(pos.x, pos.y);
+	}
+
 	pub fn has_path(&self) -> bool {
 		self.path.len() > 0
 	}
@@ -175,6 +272,13 @@ impl Creature {
 		self.path_age = 0.0;
 	}
 
+	/// Drops the path, and any path still being looked for.
+	pub fn cancel_path(&mut self) {
+		self.path.clear();
+		self.path_id = None;
+		self.path_target = None;
+	}
+
 	pub fn get_target_node(&self) -> Option<(isize,isize)> {
 		match self.has_path() {
 			false => None,
@@ -0,0 +1,136 @@
+use std::fs;
+use std::io::{stderr,Write};
+use image::RgbaImage;
+use generator::{Dungeon,DungeonParams,Features,generate_with};
+use seed::Seed;
+use graph::{Graph,GraphNode};
+use search::{SearchStrategy,AStarSearch};
+use validate::is_passable;
+use render::{self,RenderOptions};
+use gfx::{SpriteRegistry,TILE_SPRITES};
+use tiled;
+use cli::Seeds;
+
+static OUT_DIR: &'static str = "./renders/";
+
+/// What `gen` writes out.
+#[derive(RustcDecodable, Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+	/// printed to the console
+	Ascii,
+	/// images in renders/
+	Png,
+	/// Tiled maps in res/maps/, which `--map` can play
+	Json,
+}
+
+// what PNGs and Tiled maps need loaded first
+struct Assets {
+	sprites: SpriteRegistry,
+	sheet: Option<RgbaImage>,
+}
+
+// usage: gen [--count=<n>] [--seed=<seed>] [--format=<format>] [--params=<file>]
+// an error if any seed fails to generate or can't be written
+pub fn main(seeds: Seeds, format: Format, params: &DungeonParams, features: &Features) -> Result<(), String> {
+
+	let assets = try!(load_assets(format));
+
+	let mut failed = 0;
+	for seed in seeds.iter() {
+		let d = match generate_with(&seed,params,features) {
+			Ok(d) => d,
+			Err(e) => {
+				failed += 1;
+				writeln!(stderr(),"Seed {}: generation failed: {}",seed,e).ok();
+				continue;
+			}
+		};
+		if let Err(e) = write(&seed,&d,format,&assets) {
+			failed += 1;
+			writeln!(stderr(),"Seed {}: {}",seed,e).ok();
+		}
+	}
+
+	if failed > 0 {
+		return Err(format!("{} of {} seeds failed.",failed,seeds.len()));
+	}
+	Ok(())
+}
+
+fn load_assets(format: Format) -> Result<Assets, String> {
+	let sprites = match format {
+		Format::Ascii => SpriteRegistry::default(),
+		_ => try!(SpriteRegistry::load_default().map_err(|e| format!("Couldn't load sprites: {}",e))),
+	};
+	if format == Format::Json {
+		try!(sprites.check(&TILE_SPRITES).map_err(|e| format!("{}",e)));
+	}
+	let sheet = match format {
+		Format::Png => Some(try!(render::load_atlas().map_err(|e| format!("Couldn't load tile atlas: {:?}",e)))),
+		_ => None,
+	};
+	let dir = match format {
+		Format::Ascii => None,
+		Format::Png => Some(OUT_DIR),
+		Format::Json => Some(tiled::MAP_DIR),
+	};
+	if let Some(dir) = dir {
+		try!(fs::create_dir_all(dir).map_err(|e| format!("Couldn't create {}: {}",dir,e)));
+	}
+	Ok(Assets { sprites: sprites, sheet: sheet })
+}
+
+fn write(seed: &Seed, d: &Dungeon, format: Format, assets: &Assets) -> Result<(), String> {
+	match format {
+		Format::Ascii => {
+			println!("Seed {}:",seed);
+			let mut d = d.clone();
+			d.crop();
+			println!("{}",d.to_ascii());
+		}
+		Format::Png => {
+			let opts = RenderOptions {
+				entities: true,
+				path: stairs_path(d),
+			};
+			let sheet = assets.sheet.as_ref().unwrap();
+			let img = try!(render::render_dungeon(d,sheet,&assets.sprites,&opts)
+				.map_err(|e| format!("couldn't render: {}",e)));
+			let path = format!("{}dungeon-{}.png",OUT_DIR,seed);
+			try!(img.save(&path).map_err(|e| format!("couldn't write {}: {}",path,e)));
+			println!("Wrote {}",path);
+		}
+		Format::Json => {
+			let path = format!("{}dungeon-{}.json",tiled::MAP_DIR,seed);
+			try!(tiled::save(&tiled::export_dungeon(d,&assets.sprites),&path)
+				.map_err(|e| format!("couldn't write {}: {}",path,e)));
+			println!("Wrote {}",path);
+		}
+	}
+	Ok(())
+}
+
+// shortest path from the up stairs to the down stairs
+fn stairs_path(d: &Dungeon) -> Option<Vec<(isize,isize)>> {
+	let mut graph = Graph::new();
+	let open = |x: isize, y: isize| d.get_tile_type(x,y).map_or(false, is_passable);
+	for y in 0..d.height() {
+		for x in 0..d.width() {
+			if open(x,y) { graph.add_node_at(x,y); }
+		}
+	}
+	for y in 0..d.height() {
+		for x in 0..d.width() {
+			if !open(x,y) { continue; }
+			let node = GraphNode::new(x,y);
+			for &(ox,oy) in [(1,0),(0,1),(1,1),(-1,1)].iter() {
+				// no cutting corners, same as the game
+				if open(x+ox,y+oy) && open(x+ox,y) && open(x,y+oy) {
+					graph.connect_nodes(&node,&GraphNode::new(x+ox,y+oy));
+				}
+			}
+		}
+	}
+	AStarSearch::new_diagonal().solve(&graph,d.start_coords,d.end_coords)
+}
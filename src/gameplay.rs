@@ -1,7 +1,12 @@
 use std::ops::Range;
 use std::collections::HashMap;
 
-use std::sync::{Arc,RwLock};
+use std::sync::Arc;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io::{stderr,Write};
+
+use rand::{Rng,XorShiftRng};
 
 use sfml::graphics::{RenderTarget,RenderWindow};
 use sfml::graphics::View;
@@ -10,37 +15,159 @@ use sfml::graphics::rc::Sprite;
 use sfml::graphics::Color;
 use sfml::graphics::IntRect;
 use sfml::graphics::FloatRect;
-use sfml::system::Vector2f;
-use sfml::window::keyboard;
+use sfml::system::{Vector2f,Vector2u};
 use sfml::window::keyboard::Key;
+use sfml::window::event::{self,Event};
+use sfml::window::mouse::MouseButton;
 use sfml::graphics::rc::CircleShape;
 
-use old_engine::{Game,Screen};
-use generator::{Tile,Dungeon,Floor,Corridor,Door,StairsUp,StairsDown,Monster,Wall};
+use old_engine::{Game,Screen,Transition};
+use menus::{PauseScreen,InventoryScreen,GameOverScreen};
+use generator::{Tile,Dungeon,DungeonParams,Floor,Corridor,Door,StairsUp,StairsDown,Monster,Wall};
+use generator::{Entity,Treasure,Key as KeyItem};
+use generator::{DoorState,Features};
+use generator::DoorState::{Open,Closed,Locked,Secret};
 use util::get_gfx_path;
 use util::get_rc_resource;
 use util::get_sprite_coords;
 use util;
+use atlas;
+use gfx::{SpriteSetOptions,SpriteRegistry};
+use gfx::autotile;
 
 use collision::CollisionResolver;
 
-use entities::{Facing,Creature};
+use entities::Creature;
 use animation::Animation;
 
 use graph::Graph;
+use search::CostProfile;
 use solver::Solver;
+use world::{TileMap,HazardInfo};
+use seed::{Seed,Stream};
+use validate::validate;
+use hud::Hud;
+use config::Config;
+use input::{Input,Action,Bindings};
+
+/// tiles per second monsters chase at
+static MONSTER_SPEED : f32 = 1.0;
+/// how far (in tiles) searching uncovers hidden traps
+static SEARCH_RADIUS : isize = 2;
+/// chance to spot a secret door when walking up next to it
+static SECRET_NOTICE_CHANCE : f32 = 0.1;
+/// how far (in tiles) a melee attack reaches
+static ATTACK_REACH : f32 = 1.25;
+static ATTACK_DAMAGE : isize = 1;
+/// path graph terrain for a shut door, so monsters that can't open doors
+/// can route around them
+static CLOSED_DOOR : &'static str = "closed_door";
+
+/// per-type monster behavior
+struct MonsterKind {
+	/// animation set in res/gfx/anims/
+	anim: &'static str,
+	opens_doors: bool,
+	/// terrain this kind moves through at a different cost than usual
+	costs: &'static [(&'static str, f32)],
+}
+
+impl MonsterKind {
+	fn cost_profile(&self) -> CostProfile {
+		let profile = self.costs.iter().fold(CostProfile::new(), |p, &(terrain,cost)| p.with(terrain,cost));
+		if self.opens_doors { profile } else { profile.without(CLOSED_DOOR) }
+	}
+}
 
-static SOLVER_THREAD_COUNT : usize = 4;
+static MONSTER_KINDS: [MonsterKind; 4] = [
+	MonsterKind { anim: "monster_0", opens_doors: true, costs: &[] },
+	MonsterKind { anim: "monster_1", opens_doors: false, costs: &[("water", 0.5)] }, // swims
+	MonsterKind { anim: "monster_2", opens_doors: true, costs: &[] },
+	MonsterKind { anim: "monster_3", opens_doors: false, costs: &[("web", 1.0)] }, // spins them
+];
+
+/// the player's animation set in res/gfx/anims/
+static HERO_ANIM : &'static str = "hero";
+
+/// Every animation set a run draws, for loading before the window opens.
+pub fn anim_sets() -> Vec<&'static str> {
+	let mut names = vec![HERO_ANIM];
+	names.extend(MONSTER_KINDS.iter().map(|kind| kind.anim));
+	names
+}
+
+/// What every run is made from, loaded once at startup and handed from
+/// screen to screen.
+#[derive(Clone)]
+pub struct Resources {
+	pub sprites: SpriteRegistry,
+	/// res/dat/tiles.json: terrain costs, tints and hazards
+	pub tiles: TileMap,
+	/// what the generator scatters, from `tiles`
+	pub features: Features,
+	/// res/dat/bindings.json, for the hero and every menu
+	pub bindings: Bindings,
+	/// for generating the next run
+	pub params: DungeonParams,
+	pub config: Config,
+}
+
+impl Resources {
+	pub fn new(sprites: SpriteRegistry, tiles: TileMap, bindings: Bindings, params: &DungeonParams,
+			config: &Config) -> Resources {
+		Resources {
+			sprites: sprites,
+			features: Features::from_tile_map(&tiles),
+			tiles: tiles,
+			bindings: bindings,
+			params: *params,
+			config: config.clone(),
+		}
+	}
+}
+
+/// Everything needed to start a run (over).
+#[derive(Clone)]
+pub struct RunSetup {
+	pub dungeon: Dungeon,
+	/// fixed levels don't have one
+	pub seed: Option<Seed>,
+	pub res: Resources,
+}
+
+impl RunSetup {
+	pub fn start(&self) -> GameplayScreen {
+		GameplayScreen::new(self)
+	}
+}
+
+/// How a run is going, for the inventory and game over screens.
+#[derive(Clone, Debug, Default)]
+pub struct RunStats {
+	pub seed_label: String,
+	/// seconds the hero has been alive
+	pub time: f32,
+	pub kills: usize,
+	pub treasure: usize,
+	pub keys: usize,
+}
 
 pub struct GameplayScreen {
 	tile_size: usize,
 	tile_sizef: f32,
 	dungeon: Dungeon,
-	graph: Arc<RwLock<Graph>>,
+	// copy-on-write: solver threads keep the snapshot they were given
+	graph: Arc<Graph>,
+	// nodes changed while paths were being found, each with the graph
+	// version it changed at; a path from an older snapshot is only stale
+	// if it goes through one
+	graph_edits: Vec<(u64,(isize,isize))>,
 	tiles: Vec<TileData>,
 	view: View,
 	zoom_index: isize,
 	zoom_levels: Vec<f32>,
+	// tiles per second
+	hero_speed: f32,
 	creatures: Vec<Creature>,
 	debug_graph: bool,
 	debug_los: bool,
@@ -50,33 +177,76 @@ pub struct GameplayScreen {
 	vis_x: Range<isize>,
 	vis_y: Range<isize>,
 	player_idx: Option<usize>,
+	texture: Rc<RefCell<Texture>>,
+	wall_set: SpriteSetOptions,
+	// last tile the hero stood on, to notice secret doors while walking
+	hero_tile: Option<(isize,isize)>,
+	// rolls for noticing them, from the seed so a replay notices the same ones
+	secret_rng: XorShiftRng,
 	collide: CollisionResolver,
+	// sounds to play this frame, with where they came from (in px)
+	sounds: Vec<(&'static str, Vector2f)>,
+	// treasure and keys still lying around
+	items: HashMap<(isize,isize),Entity>,
+	item_circle: CircleShape,
+	stats: RunStats,
+	setup: RunSetup,
+	// screen to switch to, decided during input or logic
+	next: Option<Transition>,
+	input: Input,
+	seed_label: String,
+	hud: Hud,
 }
 
 impl GameplayScreen  {
 
-	pub fn new(dungeon: &Dungeon) -> GameplayScreen {
+	/// The seed (if any) is shown on the HUD; fixed levels don't have one.
+	pub fn new(setup: &RunSetup) -> GameplayScreen {
+		let (dungeon, sprites, seed) = (&setup.dungeon, &setup.res.sprites, setup.seed.as_ref());
+		let (tile_map, config) = (&setup.res.tiles, &setup.res.config);
 
 		let mut dungeon = dungeon.clone();
-		dungeon.shrink();
+		dungeon.crop();
+
+		// catch generator regressions (or broken maps) early, here rather
+		// than in generate so tools that validate don't hear it twice
+		if cfg!(debug_assertions) {
+			let what = seed.map_or("The fixed level".to_string(),
+				|s| format!("Seed {} (floor {})",s,setup.res.params.floor));
+			let mut out = stderr();
+			for err in validate(&dungeon).iter() {
+				writeln!(out,"Warning: {} failed validation: {}",what,err).ok();
+			}
+		}
+
 		// load tile texture file
-		let tex_path = get_gfx_path("all_tiles.png");
+		let tex_path = get_gfx_path(atlas::ATLAS_FILE);
 		let tex = Texture::new_from_file( &tex_path ).expect("Failed to load all_tiles.png");
 
 		// get refcounted version for rc::Sprite
 		let rc_tex = get_rc_resource(tex);
 
-		let tsz_init = 16;
+		// wall pieces; main() has already checked every TILE_SPRITES entry exists
+		let wall_set = sprites.require("wall").unwrap().clone();
+
+		let tsz_init = atlas::TILE_SIZE;
 		let debug_node_radius = tsz_init as f32 / 4.0;
 
+		let seed_label = match seed {
+			Some(s) => format!("Seed: {}",s),
+			None => "Fixed level".to_string(),
+		};
+
 		// init screen
 		let mut ret = GameplayScreen {
 			tile_size: tsz_init,
 			tile_sizef: tsz_init as f32,
 			dungeon: dungeon.clone(),
-			graph: Arc::new( RwLock::new( Graph::new() ) ),
+			graph: Arc::new( Graph::new() ),
+			graph_edits: Vec::new(),
 			zoom_index: 1,
-			zoom_levels: vec![1.,2.,3.,4.],
+			zoom_levels: config.zoom_levels.clone(),
+			hero_speed: config.hero_speed,
 			tiles: Vec::new(),
 			view: View::new().expect("Failed to create View"),
 			creatures: Vec::new(),
@@ -88,42 +258,46 @@ impl GameplayScreen  {
 			vis_x: 0..1,
 			vis_y: 0..1,
 			player_idx: None,
+			hero_tile: None,
+			// fixed levels all share one stream
+			secret_rng: seed.cloned().unwrap_or(Seed::from(0)).rng(setup.res.params.floor, Stream::Secrets),
+			texture: rc_tex.clone(),
+			wall_set: wall_set,
 			collide: CollisionResolver::new(),
+			hud: Hud::new(&seed_label),
+			sounds: Vec::new(),
+			items: HashMap::new(),
+			item_circle: CircleShape::new_init(debug_node_radius, 8).expect("Failed to make item circle"),
+			stats: RunStats { seed_label: seed_label.clone(), ..RunStats::default() },
+			setup: setup.clone(),
+			next: None,
+			input: Input::new(setup.res.bindings.clone()),
+			seed_label: seed_label,
 		};
+		ret.item_circle.set_origin2f(debug_node_radius,debug_node_radius);
 		ret.debug_node_circle.set_origin2f(debug_node_radius,debug_node_radius);
 		ret.debug_node_circle.set_fill_color( &Color{red: 0u8, green: 0u8, blue: 255u8, alpha: 150u8} );
 
-		for _ in 0..SOLVER_THREAD_COUNT {
+		for _ in 0..config.solver_threads {
 			ret.solvers.push(Solver::new());
 		}
 
 		// closure to get tile coordinates from tile x/y index
 		// i.e. top left tile in texture atlas is (0,0)
 		let t_sz = ret.tile_size;
-		let grab_tile_rect = |x: usize, y: usize| -> IntRect {
+		let grab_tile_rect = |(x, y): atlas::SpritePos| -> IntRect {
 			let (tx,ty) = get_sprite_coords(x,y,t_sz,t_sz);
 			IntRect{ left: tx as i32, top: ty as i32, width: t_sz as i32, height: t_sz as i32 }
 		};
 
 		// get coordinates of each tile type
-		let coords_floor = grab_tile_rect(8,6);
-		let coords_door = grab_tile_rect(3,0);
-		let coords_hall = grab_tile_rect(7,4);
-		let coords_up = grab_tile_rect(9,7);
-		let coords_dn = grab_tile_rect(10,7);
-
-		// get sprite directly from coords
-		let get_spr = |x: usize, y: usize| -> Sprite {
-			let coords = grab_tile_rect(x,y);
-			let mut spr = Sprite::new_with_texture(rc_tex.clone()).expect("erp");
-			spr.set_texture_rect(&coords);
-			spr
-		};
+		let tile_rect = |name: &str| grab_tile_rect(sprites.position(name).unwrap());
+		let coords_floor = tile_rect("floor");
+		let coords_door = tile_rect("door");
+		let coords_hall = tile_rect("corridor");
+		let coords_up = tile_rect("stairs_up");
+		let coords_dn = tile_rect("stairs_down");
 
-		let b_n = get_spr(9,3);
-		let b_s = get_spr(9,5);
-		let b_e = get_spr(10,4);
-		let b_w = get_spr(8,4);
 
 		// for each tile in the dungeon
 		for tile in dungeon.get_tile_vector().iter() {
@@ -135,7 +309,7 @@ impl GameplayScreen  {
 			// load tile coordinates based on tile type
 			let tile_coords = match tile.t {
 				Floor => vec![coords_floor],
-				Door => vec![coords_hall], // TODO draw door ON TOP of walls
+				Door(_) => vec![coords_hall],
 				Corridor => vec![coords_hall],
 				StairsUp => vec![coords_floor,coords_up],
 				StairsDown => vec![coords_floor,coords_dn],
@@ -146,6 +320,18 @@ impl GameplayScreen  {
 			let half = t_sz as f32/2.0;
 			let bounds = FloatRect::new( x as f32 - half, y as f32 - half, t_sz as f32, t_sz as f32 );
 			let mut tile_data = TileData::new(&bounds,tile);
+			match dungeon.terrain_name(tile).and_then(|t| tile_map.get(t)) {
+				None => {}
+				Some(info) => {
+					tile_data.terrain = Some(info.name.clone());
+					tile_data.cost = info.move_cost();
+					if let Some((r,g,b)) = info.tint {
+						tile_data.tint = Color{red: r, green: g, blue: b, alpha: 255};
+					}
+					tile_data.hidden = info.hazard.as_ref().map_or(false, |h| h.hidden);
+					tile_data.hazard = info.hazard.clone();
+				}
+			}
 			for coords in tile_coords.iter() {
 				let mut spr = Sprite::new_with_texture(rc_tex.clone()).expect("Failed to create sprite");
 				spr.set_texture_rect(coords);
@@ -154,21 +340,39 @@ impl GameplayScreen  {
 				tile_data.sprites.push(spr);
 			}
 
-
-			let wall_off = 1.0;
-			ret.add_wall_check(&mut tile_data, (0,-1), &b_n, wall_off);
-			ret.add_wall_check(&mut tile_data, (0,1), &b_s, wall_off);
-			ret.add_wall_check(&mut tile_data, (1,0), &b_e, wall_off);
-			ret.add_wall_check(&mut tile_data, (-1,0), &b_w, wall_off);
+			// doors get drawn on top whenever they're shut
+			match tile.t {
+				Door(_) => {
+					let mut spr = Sprite::new_with_texture(rc_tex.clone()).expect("Failed to create door sprite");
+					spr.set_texture_rect(&coords_door);
+					spr.set_origin2f(t_sz as f32/2.0, t_sz as f32/2.0);
+					spr.set_position( &Vector2f::new(x as f32,y as f32) );
+					tile_data.door_sprite = Some(spr);
+				}
+				_ => {}
+			}
 
 			ret.tiles.push(tile_data);
 		}
 
+		// autotiling needs all of a tile's neighbors first
+		for idx in 0..ret.tiles.len() {
+			let (x,y) = (ret.tiles[idx].tile.x,ret.tiles[idx].tile.y);
+			let sprites = ret.autotile_sprites((x,y));
+			ret.tiles[idx].wall_sprites = sprites;
+		}
+
 		println!("Initializing graph...");
 		// initialize graph
 		for y in 0..dungeon.height {
 			for x in 0..dungeon.width {
-				ret.graph.write().ok().expect("mt write error").add_node_at(x,y);
+				Arc::make_mut(&mut ret.graph).add_node_at(x,y);
+			}
+		}
+		for tile in ret.tiles.iter() {
+			match tile.path_terrain() {
+				None => {}
+				terrain => Arc::make_mut(&mut ret.graph).set_terrain_at(tile.tile.x,tile.tile.y,terrain),
 			}
 		}
 
@@ -179,7 +383,7 @@ impl GameplayScreen  {
 			for x in 0..dungeon.width {
 				let idx_opt = ret.tile_idx_from_coords( (x, y) );
 				let idx = idx_opt.expect("Shouldn't be negative");
-				match ret.tiles[idx].is_passable() {
+				match ret.tiles[idx].is_navigable() {
 					false => {}
 					true => {
 						// only check R, DR, D, DL
@@ -198,96 +402,112 @@ impl GameplayScreen  {
 		}
 		println!("Done with graph!");
 
-
-
-		let get_walk_cycle_frames = |x: usize, y: usize| -> Vec<IntRect> {
-			vec!(
-				grab_tile_rect(x,y),
-				grab_tile_rect(x-1,y),
-				grab_tile_rect(x,y),
-				grab_tile_rect(x+1,y)
-			)
+		// load an animation set from res/gfx/anims/
+		let load_anim = |name: &str| -> Animation {
+			// play() has already loaded every set in anim_sets()
+			let opts = sprites.anim(name).unwrap();
+			let spr = Sprite::new_with_texture(rc_tex.clone()).expect("Failed to create creature sprite");
+			Animation::new(&spr, &opts)
 		};
 
-		let get_walk_cycle = |x: usize, y: usize, length: f32| -> Animation {
-			let spr_m = get_spr(x,y);
-			let cycle_s = get_walk_cycle_frames(x,y);
-			let cycle_w = get_walk_cycle_frames(x,y+1);
-			let cycle_e = get_walk_cycle_frames(x,y+2);
-			let cycle_n = get_walk_cycle_frames(x,y+3);
-			let mut anim = Animation::new(&spr_m, &cycle_n, length);
-			anim.frame_sets.push(cycle_e);
-			anim.frame_sets.push(cycle_s);
-			anim.frame_sets.push(cycle_w);
-			anim
-		};
-
-		// load up player sprite
-		let coords_hero = grab_tile_rect(4,8);
-		let mut sprite_hero = Sprite::new_with_texture(rc_tex.clone()).expect("Failed to create hero sprite");
-		sprite_hero.set_texture_rect(&coords_hero);
-
 		// create player creature
-		let mut hero = Creature::new(&get_walk_cycle(4,8,0.5),10);
+		let mut hero = Creature::new(&load_anim(HERO_ANIM),10);
 		let (start_x, start_y) = dungeon.start_coords;
 		hero.set_position2f( (start_x*t_sz as isize) as f32, (start_y*t_sz as isize) as f32 );
 		hero.player = true;
 
-
-
 		// a bunch of monsters
-		let coords_slime = grab_tile_rect(10,8);
-		let mut sprite_slime = Sprite::new_with_texture(rc_tex.clone()).expect("Failed to load slime sprite");
-		sprite_slime.set_texture_rect(&coords_slime);
-
-		let monster_cycles = [
-			get_walk_cycle(10,8,1.0),
-			get_walk_cycle(10,12,1.0),
-			get_walk_cycle(7,12,1.0),
-			get_walk_cycle(4,12,1.0),
-			// get_walk_cycle(1,12,1.0), // slime
-		];
-
-		// find and create monsters
+		let monster_cycles: Vec<Animation> = MONSTER_KINDS.iter()
+			.map(|kind| load_anim(kind.anim))
+			.collect();
+
+		// find and create monsters, and leave the loot lying around
 		for tile in dungeon.tiles.iter() {
 			match tile.e {
 				Some(Monster(num)) => {
 					let idx = num % monster_cycles.len();
 					let mut slime = Creature::new(&monster_cycles[idx],5);
+					slime.opens_doors = MONSTER_KINDS[idx].opens_doors;
+					slime.costs = MONSTER_KINDS[idx].cost_profile();
 					slime.set_position2f( (tile.x*t_sz as isize) as f32, (tile.y*t_sz as isize) as f32 );
-					slime.anim.timer = (((num % 100) as f32) / 100.0) * slime.anim.length;
-					slime.anim.update(0.0);
 					ret.creatures.push(slime);
 				}
+				Some(item @ Treasure) | Some(item @ KeyItem) => {
+					ret.items.insert((tile.x,tile.y),item);
+				}
 				_ => {}
 			}
 		}
 
+		let hp = hero.get_health();
 		ret.creatures.push(hero);
+		ret.show_health(hp);
 		ret
 	}
 
+	/// For driving the hero from a script instead of the keyboard.
+	pub fn input_mut(&mut self) -> &mut Input {
+		&mut self.input
+	}
+
+	fn do_action(&mut self, action: Action) {
+		match action {
+			Action::ZoomOut => self.zoom_index -= 1,
+			Action::ZoomIn => self.zoom_index += 1,
+			Action::Door => self.toggle_adjacent_door(),
+			Action::Search => self.search(),
+			Action::Attack => self.hero_attack(),
+			Action::Pause => {
+				let pause = PauseScreen::new(&self.stats, &self.setup);
+				self.next = Some(Transition::Push(Box::new(pause)));
+			}
+			Action::Inventory => {
+				self.next = Some(Transition::Push(Box::new(InventoryScreen::new(&self.stats,&self.setup.res.bindings))));
+			}
+			Action::DebugGraph => self.debug_graph = !self.debug_graph,
+			Action::DebugLos => self.debug_los = !self.debug_los,
+			Action::DebugAll => {
+				let all = self.debug_los && self.debug_graph;
+				let target = !all;
+				self.debug_los = target;
+				self.debug_graph = target;
+			}
+			// for menus
+			Action::Confirm => {}
+			// held, not pressed; see logic()
+			Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight => {}
+		}
+	}
+
 	fn logic(&mut self, game : &mut Game, window : &mut RenderWindow, delta : f32) {
+		self.hud.update(delta);
+
+		// presses since last tick
+		self.input.update();
+		for action in self.input.take_pressed() {
+			self.do_action(action);
+		}
+
 		// figure out zoom level
 		self.zoom_index = util::clamp(self.zoom_index,0,self.zoom_levels.len() as isize-1);
 		let mag = self.zoom_levels[self.zoom_index as usize];
 
 		// if no player, enable panning? sure.
-		let pan_spd = 16.*16.*delta/mag;
+		let pan_spd = 16.*self.tile_sizef*delta/mag;
 
-		let go_l = keyboard::is_key_pressed(Key::Left);
-		let go_r = keyboard::is_key_pressed(Key::Right);
-		let go_u = keyboard::is_key_pressed(Key::Up);
-		let go_d = keyboard::is_key_pressed(Key::Down);
+		// which way and how fast, from the keys or stick
+		let movement = self.input.movement();
 
-		let mut pan = Vector2f::new(0.,0.);
-
-		if go_l { pan.x -= pan_spd };
-		if go_r { pan.x += pan_spd };
-		if go_u { pan.y -= pan_spd };
-		if go_d { pan.y += pan_spd };
+		if let Some((rads, speed)) = movement {
+			let pan = pan_spd*speed;
+			self.view.move_(&Vector2f::new(pan*rads.cos(), pan*rads.sin()));
+		}
 
-		self.view.move_(&pan);
+		// clear out the dead once they're done falling over
+		self.creatures.retain(|c| c.player || !c.is_gone());
+		for creature in self.creatures.iter_mut() {
+			creature.begin_tick();
+		}
 
 		// depth sort
 		self.sprite_depth_sort();
@@ -311,58 +531,98 @@ impl GameplayScreen  {
 		match player {
 			None => {}
 			Some(hero) => {
-				let dist = 64.*delta;
-
-				let angle = match(go_u,go_d,go_l,go_r) {
-					(false,false,false,true) => Some(0.),
-					(false,true,false,true) => Some(45.),
-					(false,true,false,false) => Some(90.),
-					(false,true,true,false) => Some(135.),
-					(false,false,true,false) => Some(180.),
-					(true,false,true,false) => Some(225.),
-					(true,false,false,false) => Some(270.),
-					(true,false,false,true) => Some(315.),
-					_ => None
-				};
-
-				// move player
-				match angle {
+				let dist = self.hero_speed*self.tile_sizef*delta/self.move_cost(hero);
+
+				// move player; steering by hand cancels any clicked path
+				let movement = if self.creatures[hero].is_dead() { None } else { movement };
+				self.creatures[hero].set_moving(movement.is_some());
+				match movement {
 					None => { }
-					Some(deg) => {
+					Some((rads, speed)) => {
 						let mut guy = &mut self.creatures[hero];
-						guy.move_polar_deg(dist,deg);
-						guy.set_facing( Facing::from_deg(deg) );
-						guy.update_anim(delta);
+						guy.cancel_path();
+						guy.move_polar_rad(dist*speed,rads);
+						guy.set_facing_rad(rads);
 					}
 				}
 
 				// get solutions
-				let mut path_map: HashMap<usize,Vec<(isize,isize)>> = HashMap::new();
-				for solver in self.solvers.iter_mut() {
-					loop {
-						match solver.poll() {
-							None => break,
-							Some(soln) => {
-								let id = soln.id;
-								let path = match soln.path {
-									None => Vec::new(),
-									Some(path) => path
-								};
-								path_map.insert(id,path);
+				// paths through tiles that changed since come back as None
+				let mut path_map: HashMap<usize,Option<Vec<(isize,isize)>>> = HashMap::new();
+				{
+					let edits = &self.graph_edits;
+					for solver in self.solvers.iter_mut() {
+						loop {
+							match solver.poll() {
+								None => break,
+								Some(soln) => {
+									let id = soln.id;
+									let v = soln.graph_version;
+									let path = match soln.path {
+										// a way may have opened up since
+										None if edits.iter().any(|&(at,_)| at >= v) => None,
+										None => Some(Vec::new()),
+										Some(ref path) if edits.iter().any(|&(at,c)| at >= v && path.contains(&c)) => None,
+										Some(path) => Some(path)
+									};
+									path_map.insert(id,path);
+								}
 							}
 						}
 					}
 				}
+				// once every search is back, no snapshot predates the edits
+				if self.solvers.iter().all(|s| s.get_problem_count() == 0) {
+					self.graph_edits.clear();
+				}
+
+				// walk where the hero was clicked to
+				if let Some(id) = self.creatures[hero].path_id {
+					match path_map.remove(&id) {
+						None => {}
+						// stale -- ask again for the same tile
+						Some(None) => {
+							let target = self.creatures[hero].path_target;
+							self.creatures[hero].cancel_path();
+							if let Some(target) = target {
+								self.request_path(hero,target);
+							}
+						}
+						Some(Some(ref path)) => {
+							self.creatures[hero].path_id = None;
+							self.creatures[hero].set_path(path);
+							self.creatures[hero].pop_path_node();
+						}
+					}
+				}
+				if self.creatures[hero].is_dead() {
+					self.creatures[hero].cancel_path();
+				} else if movement.is_none() && self.creatures[hero].has_path() {
+					self.creatures[hero].set_moving(true);
+					self.follow_path(hero, dist);
+				}
 
 				// chase player!
 				let hero_pos = self.creatures[hero].get_position();
+				let hero_alive = !self.creatures[hero].is_dead();
 				for i in 0..self.creatures.len() {
-					if i == hero { continue; }
+					if i == hero || self.creatures[i].is_dead() { continue; }
 
 					let monster_pos = self.creatures[i].get_position();
 
 					let sees_player = self.los(&hero_pos,&monster_pos);
 
+					// close enough to swing -- stand still while doing it
+					if hero_alive && sees_player && self.in_reach(&monster_pos,&hero_pos) {
+						let dif = hero_pos - monster_pos;
+						self.creatures[i].set_facing_rad( dif.y.atan2(dif.x) );
+						self.creatures[i].attack();
+					}
+					if self.creatures[i].is_attacking() {
+						self.creatures[i].set_moving(false);
+						continue;
+					}
+
 					let path_id = self.creatures[i].path_id;
 
 					let searching_path = self.creatures[i].path_id.is_some();
@@ -373,7 +633,12 @@ impl GameplayScreen  {
 							let path_opt = path_map.remove(id);
 							match path_opt {
 								None => {},
-								Some(ref path) => {
+								// stale -- forget the target so it gets asked for again
+								Some(None) => {
+									self.creatures[i].path_id = None;
+									self.creatures[i].path_target = None;
+								}
+								Some(Some(ref path)) => {
 									self.creatures[i].path_id = None;
 									//match path.len() {
 										//0 => {},
@@ -387,6 +652,7 @@ impl GameplayScreen  {
 						}
 					}
 					let has_path = self.creatures[i].has_path();
+					self.creatures[i].set_moving(has_path);
 
 					let req_path = sees_player &&
 						( (!has_path) || (!searching_path && self.creatures[i].path_age > 0.25) );
@@ -398,35 +664,8 @@ impl GameplayScreen  {
 					// TODO reconcile this with collision somehow
 					if has_path {
 						self.creatures[i].path_age += delta;
-						let tsz = self.tile_size as f32;
-						let first_coords = self.creatures[i].get_target_node().expect("UGH");
-						let (tx,ty) = first_coords;
-						let (wx,wy) = (
-							tx as f32 * tsz,
-							ty as f32 * tsz
-						);
-						let wv = Vector2f::new(wx,wy);
-
-						let chase_dist = 16.0 * delta;
-						let mut dist_remaining = chase_dist;
-
-						self.creatures[i].update_anim(delta);
-						while dist_remaining > 0.0 && self.creatures[i].has_path() {
-							let pos_dif = wv - monster_pos;
-							let dif_len = (pos_dif.x*pos_dif.x + pos_dif.y*pos_dif.y).sqrt();
-							if dif_len < dist_remaining {
-								let mut cr = &mut self.creatures[i];
-								cr.set_position(&wv);
-								cr.pop_path_node();
-								dist_remaining -= dif_len;
-							} else {
-								let (dx,dy) = (pos_dif.x,pos_dif.y);
-								let rads = dy.atan2(dx);
-								self.creatures[i].move_polar_rad( chase_dist, rads );
-								self.creatures[i].set_facing_rad( rads );
-								dist_remaining = 0.0;
-							}
-						}
+						let chase_dist = MONSTER_SPEED * self.tile_sizef * delta / self.move_cost(i);
+						self.follow_path(i, chase_dist);
 					}
 				}
 			}
@@ -436,10 +675,33 @@ impl GameplayScreen  {
 		// collision
 		self.resolve_all_collisions();
 
-		// updates
-		// for creature in self.creatures.iter_mut() {
-		// 	creature.update_anim(delta);
-		// }
+		// traps, lava, etc.
+		self.update_hazards(delta);
+		self.notice_secrets();
+
+		// animations, and whatever their frame events set off
+		self.update_anims(delta);
+
+		if let Some(hero) = player {
+			if self.creatures[hero].is_gone() {
+				if self.next.is_none() {
+					let over = GameOverScreen::new(&self.stats, &self.setup);
+					self.next = Some(Transition::Push(Box::new(over)));
+				}
+			} else if !self.creatures[hero].is_dead() {
+				self.stats.time += delta;
+				self.pick_up_items(hero);
+			}
+		}
+
+		// hear everything from where the hero stands
+		if let Some(hero) = player {
+			let pos = self.creatures[hero].get_position();
+			game.audio.set_listener(pos.x / self.tile_sizef, pos.y / self.tile_sizef);
+		}
+		for (name, pos) in self.sounds.drain(..) {
+			game.audio.play_at(name, pos.x / self.tile_sizef, pos.y / self.tile_sizef);
+		}
 
 		// set up screen view
 		self.view.set_size2f( (window.get_size().x as f32)/mag,
@@ -488,7 +750,7 @@ impl GameplayScreen  {
 								let (hero_x,hero_y) = hero_coords;
 								let t = self.tiles[idx].tile;
 								
-								if self.los_coords(hero_x,hero_y,t.x,t.y) {
+								if self.sees_tile(hero_x,hero_y,t.x,t.y) {
 									self.tiles[idx].seen = true;
 									self.tiles[idx].visible = true;
 								} else {
@@ -497,12 +759,15 @@ impl GameplayScreen  {
 							}
 						}
 						// color
-						let color = if self.tiles[idx].visible || self.debug_los {
+						let shade = if self.tiles[idx].visible || self.debug_los {
 							Color::white()
 						} else {
 							Color{red: 100, green: 75, blue: 75, alpha: 255}
 						};
+						let color = modulate(&shade, &self.tiles[idx].visible_tint());
 						for spr in self.tiles[idx].sprites.iter_mut() {spr.set_color(&color);}
+						for spr in self.tiles[idx].door_sprite.iter_mut() {spr.set_color(&color);}
+						for spr in self.tiles[idx].wall_sprites.iter_mut() {spr.set_color(&color);}
 					}
 				}
 			}
@@ -511,23 +776,103 @@ impl GameplayScreen  {
 
 	fn request_path_update(&mut self, i: usize, hero: usize) {
 		let hero_coords = self.tile_coords_from_creature(&self.creatures[hero]);
-		let new_target = Some(hero_coords);
-		if self.creatures[i].path_target == new_target { return; }
+		if self.creatures[i].path_target == Some(hero_coords) { return; }
+		self.creatures[i].awake = true;
+		self.request_path(i,hero_coords);
+	}
+
+	/// Asks a solver for a path from creature `i` to `target`; the answer
+	/// turns up in logic() under the creature's path_id.
+	fn request_path(&mut self, i: usize, target: (isize,isize)) {
 		let id = self.path_count;
 		self.path_count += 1;
 		self.creatures[i].path_id = Some(id);
-		self.creatures[i].path_target = new_target;
-		self.creatures[i].awake = true;
+		self.creatures[i].path_target = Some(target);
 		let rawr_coords = self.tile_coords_from_creature(&self.creatures[i]);
 		let solver_idx = i % self.solvers.len();
 		self.solvers[solver_idx].queue_solve(
 			id,
 			self.graph.clone(),
 			rawr_coords,
-			hero_coords
+			target,
+			self.creatures[i].costs.clone()
 		);
 	}
 
+	/// Walks creature `i` up to `dist` px along its path, opening doors on
+	/// the way if it can.
+	fn follow_path(&mut self, i: usize, dist: f32) {
+		let tsz = self.tile_size as f32;
+		let mut dist_remaining = dist;
+
+		// a step can pass through several nodes, so each time round the
+		// target and position are wherever the last one left them
+		while dist_remaining > 0.0 {
+			let node = match self.creatures[i].get_target_node() {
+				Some(coords) => coords,
+				None => return
+			};
+			let (tx,ty) = node;
+
+			// the door may have shut (or locked) since the path was found;
+			// forget the target too, so the next request plans around it
+			match self.door_state(node) {
+				Some(Closed) if self.creatures[i].opens_doors => {
+					self.set_door_state(node,Open);
+				}
+				Some(Closed) | Some(Locked) => {
+					self.creatures[i].set_path(&Vec::new());
+					self.creatures[i].path_target = None;
+					return;
+				}
+				_ => {}
+			}
+
+			let wv = Vector2f::new(tx as f32 * tsz, ty as f32 * tsz);
+			let pos_dif = wv - self.creatures[i].get_position();
+			let dif_len = (pos_dif.x*pos_dif.x + pos_dif.y*pos_dif.y).sqrt();
+			if dif_len < dist_remaining {
+				let mut cr = &mut self.creatures[i];
+				cr.set_position(&wv);
+				cr.pop_path_node();
+				dist_remaining -= dif_len;
+			} else {
+				let rads = pos_dif.y.atan2(pos_dif.x);
+				self.creatures[i].move_polar_rad( dist_remaining, rads );
+				self.creatures[i].set_facing_rad( rads );
+				dist_remaining = 0.0;
+			}
+		}
+	}
+
+	/// Where a pixel in the window is in the world, at the current zoom.
+	fn screen_to_world(&self, window_size: Vector2u, x: i32, y: i32) -> Vector2f {
+		let index = util::clamp(self.zoom_index,0,self.zoom_levels.len() as isize-1);
+		let mag = self.zoom_levels[index as usize];
+		let center = self.view.get_center();
+		Vector2f::new(
+			center.x + (x as f32 - window_size.x as f32/2.0)/mag,
+			center.y + (y as f32 - window_size.y as f32/2.0)/mag
+		)
+	}
+
+	/// Sends the hero walking to a tile, if it's somewhere they know about
+	/// and could get to.
+	fn click_to_move(&mut self, target: (isize,isize)) {
+		let hero = match self.creatures.iter().position(|c| c.player) {
+			Some(hero) => hero,
+			None => return
+		};
+		if self.creatures[hero].is_dead() { return; }
+		let known = match self.tile_data_from_coords(target) {
+			Some(tile) => tile.is_navigable() && (tile.seen || tile.visible || self.debug_los),
+			None => false
+		};
+		if !known { return; }
+		self.creatures[hero].cancel_path();
+		self.request_path(hero,target);
+	}
+
 	fn get_active_tiles(&self, bounds: &FloatRect) -> Vec<(isize,isize)> {
 		let mut active_tiles = Vec::new();
 
@@ -549,6 +894,252 @@ impl GameplayScreen  {
 		active_tiles
 	}
 
+	fn update_anims(&mut self, delta: f32) {
+		for i in 0..self.creatures.len() {
+			self.creatures[i].update_anim(delta);
+			for event in self.creatures[i].take_anim_events() {
+				match &event.name[..] {
+					"hit" => self.land_hit(i),
+					"footstep" => {
+						let pos = self.creatures[i].get_position();
+						self.sounds.push(("footstep", pos));
+					}
+					_ => {}
+				}
+			}
+		}
+	}
+
+	// creature i's attack connects with everyone on the other side in reach
+	fn land_hit(&mut self, i: usize) {
+		let pos = self.creatures[i].get_position();
+		let player = self.creatures[i].player;
+		for j in 0..self.creatures.len() {
+			if j == i || self.creatures[j].player == player || self.creatures[j].is_dead() {
+				continue;
+			}
+			let target = self.creatures[j].get_position();
+			if !self.in_reach(&pos,&target) { continue; }
+			self.creatures[j].damage(ATTACK_DAMAGE);
+			if player && self.creatures[j].is_dead() {
+				self.stats.kills += 1;
+			}
+			let sound = if self.creatures[j].is_dead() { "death" } else { "hit" };
+			self.sounds.push((sound, target));
+			if self.creatures[j].player {
+				let hp = self.creatures[j].get_health();
+				self.show_health(hp);
+			}
+		}
+	}
+
+	fn in_reach(&self, a: &Vector2f, b: &Vector2f) -> bool {
+		let dif = *b - *a;
+		let reach = ATTACK_REACH * self.tile_sizef;
+		dif.x*dif.x + dif.y*dif.y <= reach*reach
+	}
+
+	fn pick_up_items(&mut self, hero: usize) {
+		let coords = self.tile_coords_from_creature(&self.creatures[hero]);
+		match self.items.remove(&coords) {
+			Some(Treasure) => {
+				self.stats.treasure += 1;
+				self.hud.message("You found some treasure!");
+			}
+			Some(KeyItem) => {
+				self.stats.keys += 1;
+				self.hud.message("You found a key!");
+			}
+			_ => {}
+		}
+	}
+
+	fn hero_attack(&mut self) {
+		for creature in self.creatures.iter_mut() {
+			if creature.player {
+				creature.attack();
+			}
+		}
+	}
+
+	fn update_hazards(&mut self, delta: f32) {
+		for i in 0..self.creatures.len() {
+			let coords = self.tile_coords_from_creature(&self.creatures[i]);
+			let hazard_opt = self.tile_data_from_coords(coords).and_then(|t| t.hazard.clone());
+			let hazard = match hazard_opt {
+				Some(hazard) => hazard,
+				None => {
+					self.creatures[i].hazard_tile = None;
+					continue;
+				}
+			};
+			if !self.hazard_hits(i, coords, &hazard, delta) { continue; }
+
+			// stepping on a trap gives it away
+			self.reveal_hazard(coords);
+			let pos = self.creatures[i].get_position();
+			self.sounds.push(("trap", pos));
+			if hazard.arrows {
+				if self.creatures[i].player {
+					self.hud.message("Click! Arrows fly out of the walls!");
+				}
+				self.fire_arrows(coords, hazard.damage);
+				continue;
+			}
+			self.creatures[i].damage(hazard.damage);
+			if self.creatures[i].player {
+				let hp = self.creatures[i].get_health();
+				self.show_health(hp);
+			}
+		}
+	}
+
+	// the HUD line: seed, then the player's health
+	fn show_health(&mut self, hp: isize) {
+		let line = format!("{}   HP {}", self.seed_label, hp);
+		self.hud.set_line(&line);
+	}
+
+	// arrows fly along the plate's row and column from wall to wall, and
+	// hit everyone in the way -- not just whoever stepped on it
+	fn fire_arrows(&mut self, coords: (isize,isize), damage: isize) {
+		let (px,py) = coords;
+		let mut line = vec![coords];
+		for &(ox,oy) in [(0,-1),(1,0),(0,1),(-1,0)].iter() {
+			let (mut x, mut y) = (px+ox, py+oy);
+			while self.tile_data_from_coords((x,y)).map_or(false, |t| t.is_passable()) {
+				line.push((x,y));
+				x += ox;
+				y += oy;
+			}
+		}
+		for j in 0..self.creatures.len() {
+			if self.creatures[j].is_dead() { continue; }
+			let at = self.tile_coords_from_creature(&self.creatures[j]);
+			if !line.contains(&at) { continue; }
+			self.creatures[j].damage(damage);
+			let pos = self.creatures[j].get_position();
+			self.sounds.push(("hit", pos));
+			if self.creatures[j].player {
+				let hp = self.creatures[j].get_health();
+				self.show_health(hp);
+			}
+		}
+	}
+
+	// does the hazard under creature i go off this frame?
+	fn hazard_hits(&mut self, i: usize, coords: (isize,isize), hazard: &HazardInfo, delta: f32) -> bool {
+		let creature = &mut self.creatures[i];
+		if creature.hazard_tile != Some(coords) {
+			creature.hazard_tile = Some(coords);
+			creature.hazard_timer = 0.0;
+			return true;
+		}
+		match hazard.interval {
+			None => false,
+			Some(interval) => {
+				creature.hazard_timer += delta;
+				if creature.hazard_timer >= interval {
+					creature.hazard_timer -= interval;
+					true
+				} else {
+					false
+				}
+			}
+		}
+	}
+
+	fn reveal_hazard(&mut self, coords: (isize,isize)) {
+		let idx = match self.tile_idx_from_coords(coords) {
+			Some(idx) if self.tiles[idx].hidden => idx,
+			_ => return
+		};
+		self.tiles[idx].hidden = false;
+		let (x,y) = coords;
+		let terrain = self.tiles[idx].path_terrain();
+		self.graph_at((x,y)).set_terrain_at(x,y,terrain);
+		// everyone should route around it now
+		for creature in self.creatures.iter_mut() {
+			creature.path_target = None;
+		}
+	}
+
+	// look around the hero for traps, and for secret doors right next to them
+	fn search(&mut self) {
+		let hero = match self.creatures.iter().position(|c| c.player) {
+			Some(hero) => hero,
+			None => return
+		};
+		let (hx,hy) = self.tile_coords_from_creature(&self.creatures[hero]);
+		let mut traps = 0;
+		for y in (hy-SEARCH_RADIUS)..(hy+SEARCH_RADIUS+1) {
+			for x in (hx-SEARCH_RADIUS)..(hx+SEARCH_RADIUS+1) {
+				if self.tile_data_from_coords((x,y)).map_or(false, |t| t.hidden) {
+					self.reveal_hazard((x,y));
+					traps += 1;
+				}
+			}
+		}
+		let mut doors = 0;
+		for &(ox,oy) in [(0,-1),(1,0),(0,1),(-1,0)].iter() {
+			if self.reveal_secret_door((hx+ox,hy+oy)) {
+				doors += 1;
+			}
+		}
+		match (traps,doors) {
+			(0,0) => self.hud.message("You search carefully, but find nothing."),
+			(n,0) => self.hud.message(&format!("You found {} hidden trap(s)!", n)),
+			(_,_) => {}
+		}
+	}
+
+	// sometimes spot a secret door just by walking past it
+	fn notice_secrets(&mut self) {
+		let hero = match self.creatures.iter().position(|c| c.player) {
+			Some(hero) => hero,
+			None => return
+		};
+		let coords = self.tile_coords_from_creature(&self.creatures[hero]);
+		if self.hero_tile == Some(coords) { return; }
+		self.hero_tile = Some(coords);
+		let (hx,hy) = coords;
+		for &(ox,oy) in [(0,-1),(1,0),(0,1),(-1,0)].iter() {
+			let door = (hx+ox,hy+oy);
+			if self.door_state(door) == Some(Secret) && self.secret_rng.gen::<f32>() < SECRET_NOTICE_CHANCE {
+				self.reveal_secret_door(door);
+			}
+		}
+	}
+
+	fn reveal_secret_door(&mut self, coords: (isize,isize)) -> bool {
+		if self.door_state(coords) != Some(Secret) { return false; }
+		self.set_door_state(coords,Closed);
+		// the walls around it were tiled as if it was one of them
+		let (x,y) = coords;
+		for ny in (y-1)..(y+2) {
+			for nx in (x-1)..(x+2) {
+				match self.tile_idx_from_coords((nx,ny)) {
+					Some(idx) => {
+						let sprites = self.autotile_sprites((nx,ny));
+						self.tiles[idx].wall_sprites = sprites;
+					}
+					None => {}
+				}
+			}
+		}
+		self.hud.message("You found a secret door!");
+		true
+	}
+
+	/// how much slower than normal the creature moves on its current tile
+	fn move_cost(&self, i: usize) -> f32 {
+		let creature = &self.creatures[i];
+		match self.tile_data_from_coords(self.tile_coords_from_creature(creature)) {
+			None => 1.0,
+			Some(t) => creature.costs.cost(t.terrain.as_ref().map(|name| &name[..]), t.cost)
+		}
+	}
+
 	fn tile_coords_from_creature(&self, creature: &Creature) -> (isize,isize) {
 		let pos = creature.get_position();
 		self.tile_coords_from_position((pos.x,pos.y))
@@ -676,7 +1267,7 @@ impl GameplayScreen  {
 		}
 	}
 
-	fn draw(&self, game : &mut Game, window : &mut RenderWindow) {
+	fn render(&self, game : &mut Game, window : &mut RenderWindow) {
 
 		window.set_view( &self.view );
 
@@ -701,8 +1292,34 @@ impl GameplayScreen  {
 					Some(idx) => {
 						// first draw sprites
 						if self.tiles[idx].visible || self.tiles[idx].seen || self.debug_los {
-							for sprite in self.tiles[idx].sprites.iter() {
-								window.draw(sprite);
+							// secret doors are drawn like walls
+							if self.tiles[idx].looks_like_wall() {
+								for sprite in self.tiles[idx].wall_sprites.iter() {
+									window.draw(sprite);
+								}
+							} else {
+								for sprite in self.tiles[idx].sprites.iter() {
+									window.draw(sprite);
+								}
+								for sprite in self.tiles[idx].wall_sprites.iter() {
+									window.draw(sprite);
+								}
+								match (self.tiles[idx].door_state(), &self.tiles[idx].door_sprite) {
+									(Some(state), &Some(ref sprite)) if state.blocks() => window.draw(sprite),
+									_ => {}
+								}
+							}
+						}
+						// anything lying around
+						if self.tiles[idx].visible || self.debug_los {
+							if let Some(item) = self.items.get(&(x,y)) {
+								let mut circle = self.item_circle.clone();
+								circle.set_fill_color( &match *item {
+									Treasure => Color{red: 255u8, green: 215u8, blue: 0u8, alpha: 255u8},
+									_ => Color{red: 0u8, green: 200u8, blue: 255u8, alpha: 255u8},
+								});
+								circle.set_position2f(x as f32 * self.tile_sizef, y as f32 * self.tile_sizef);
+								window.draw(&circle);
 							}
 						}
 						// then maybe nodes
@@ -714,6 +1331,20 @@ impl GameplayScreen  {
 							);
 							window.draw(&circle);
 						}
+						// and hazards, including the ones nobody's found yet
+						if self.tiles[idx].hazard.is_some() && self.debug_graph {
+							let mut circle = self.debug_node_circle.clone();
+							circle.set_fill_color( &if self.tiles[idx].hidden {
+								Color{red: 255u8, green: 0u8, blue: 255u8, alpha: 150u8}
+							} else {
+								Color{red: 255u8, green: 0u8, blue: 0u8, alpha: 150u8}
+							});
+							circle.set_position2f(
+								self.tiles[idx].tile.x as f32 * self.tile_size as f32,
+								self.tiles[idx].tile.y as f32 * self.tile_size as f32
+							);
+							window.draw(&circle);
+						}
 					}
 
 				}
@@ -727,51 +1358,59 @@ impl GameplayScreen  {
 				Some(ref pos) => {
 					if self.debug_los || self.los(pos,&creature.get_position()) {
 						creature.draw(window);
-						if self.debug_graph {
-							match creature.get_path() {
-								None => {}
-								Some(path) => {
-									// draw_line(&mut self, window: &mut RenderWindow,
-									// start: &Vector2f, end: &Vector2f, color: &Color)
-
-									// draw red line from creature to current target node
-									let (start_tx,start_ty) = path[0];
-									let start_wx = start_tx as f32 * self.tile_sizef;
-									let start_wy = start_ty as f32 * self.tile_sizef;
-									let cpos = creature.get_position();
-									let npos = Vector2f::new(start_wx,start_wy);
-									game.draw_line(
-										window,
-										&cpos,
-										&npos,
-										&Color::red()
-									);
-
-									// if path len > 1, connect all nodes
-									if path.len() > 1 {
-										for i in 0..path.len()-1 {
-											let (atx,aty) = path[i];
-											let (btx,bty) = path[i+1];
-											let t = self.tile_sizef;
-											let (awx,awy): (f32, f32) = (atx as f32*t,aty as f32*t);
-											let (bwx,bwy): (f32, f32) = (btx as f32*t,bty as f32*t);
-											let apos = Vector2f::new(awx,awy);
-											let bpos = Vector2f::new(bwx,bwy);
-											game.draw_line(
-												window,
-												&apos,
-												&bpos,
-												&Color{ red:255, green:255, blue:255, alpha:150 }
-											);
-										}
-									}
-								}
-							}
+						// monsters' paths are debug info; the hero's is where they were sent
+						if self.debug_graph || creature.player {
+							self.draw_path(game,window,creature);
 						}
 					}
 				}
 			}
 		}
+
+		self.hud.draw(window);
+	}
+
+	/// Red line to where the creature is headed next, then the rest of its path.
+	fn draw_path(&self, game : &mut Game, window : &mut RenderWindow, creature: &Creature) {
+		match creature.get_path() {
+			None => {}
+			Some(path) => {
+				// draw_line(&mut self, window: &mut RenderWindow,
+				// start: &Vector2f, end: &Vector2f, color: &Color)
+
+				// draw red line from creature to current target node
+				let (start_tx,start_ty) = path[0];
+				let start_wx = start_tx as f32 * self.tile_sizef;
+				let start_wy = start_ty as f32 * self.tile_sizef;
+				let cpos = creature.get_position();
+				let npos = Vector2f::new(start_wx,start_wy);
+				game.draw_line(
+					window,
+					&cpos,
+					&npos,
+					&Color::red()
+				);
+
+				// if path len > 1, connect all nodes
+				if path.len() > 1 {
+					for i in 0..path.len()-1 {
+						let (atx,aty) = path[i];
+						let (btx,bty) = path[i+1];
+						let t = self.tile_sizef;
+						let (awx,awy): (f32, f32) = (atx as f32*t,aty as f32*t);
+						let (bwx,bwy): (f32, f32) = (btx as f32*t,bty as f32*t);
+						let apos = Vector2f::new(awx,awy);
+						let bpos = Vector2f::new(bwx,bwy);
+						game.draw_line(
+							window,
+							&apos,
+							&bpos,
+							&Color{ red:255, green:255, blue:255, alpha:150 }
+						);
+					}
+				}
+			}
+		}
 	}
 
 	fn los(&self, a: &Vector2f, b: &Vector2f) -> bool {
@@ -785,8 +1424,19 @@ impl GameplayScreen  {
 		self.tiles[idx].is_clear()
 	}
 
-	// TODO better LOS algorithm
 	fn los_coords(&self, x1: isize, y1: isize, x2: isize, y2: isize) -> bool {
+		self.los_line(x1,y1,x2,y2).iter().all(|&coords| self.get_tile_los(coords))
+	}
+
+	// can the tile be seen? unlike los_coords, it may block sight itself (walls, doors)
+	fn sees_tile(&self, x1: isize, y1: isize, x2: isize, y2: isize) -> bool {
+		self.los_line(x1,y1,x2,y2).iter()
+			.filter(|&&coords| coords != (x2,y2))
+			.all(|&coords| self.get_tile_los(coords))
+	}
+
+	// TODO better LOS algorithm
+	fn los_line(&self, x1: isize, y1: isize, x2: isize, y2: isize) -> Vec<(isize,isize)> {
 		let mut xstep;
 		let mut ystep;
 		let mut error;
@@ -855,40 +1505,56 @@ impl GameplayScreen  {
 			}
 		}
 
-		for coords in points.iter() {
-			if !self.get_tile_los(*coords) {
-				return false;
-			}
-		}
-
-		true
+		points
 	}
 
 }
 
 impl Screen for GameplayScreen {
 
+	fn init(&mut self, game : &mut Game, window : &mut RenderWindow) {
+		window.set_title(&format!("Rusty Rogue - {}",self.seed_label));
+		game.audio.play_floor_music(self.setup.res.params.floor);
+	}
+
+	fn resume(&mut self, game : &mut Game, window : &mut RenderWindow) {
+		window.set_title(&format!("Rusty Rogue - {}",self.seed_label));
+	}
+
 	fn key_press(&mut self, game : &mut Game, window : &mut RenderWindow, key : Key) -> bool {
-		match key {
-			Key::Comma => {self.zoom_index -= 1;true}
-			Key::Period => {self.zoom_index += 1;true}
-			Key::G => {self.debug_graph = !self.debug_graph;true}
-			Key::L => {self.debug_los = !self.debug_los;true}
-			Key::D => {
-				let all = self.debug_los && self.debug_graph;
-				let target = !all;
-				self.debug_los = target;
-				self.debug_graph = target;
+		// acted on at the start of the next tick
+		self.input.key_press(key)
+	}
+
+	fn event(&mut self, game : &mut Game, window : &mut RenderWindow, e : Event) -> bool {
+		match e {
+			event::JoystickButtonPressed{button, ..} => self.input.button_press(button),
+			event::MouseButtonPressed{button: MouseButton::MouseLeft, x, y} => {
+				let pos = self.screen_to_world(window.get_size(), x, y);
+				let target = self.tile_coords_from_position((pos.x,pos.y));
+				self.click_to_move(target);
 				true
 			}
 			_ => false
 		}
 	}
 
-	fn update(&mut self, game : &mut Game, window : &mut RenderWindow, delta : f32) -> Option<Box<Screen>> {
+	fn update(&mut self, game : &mut Game, window : &mut RenderWindow, delta : f32) -> Transition {
 		self.logic(game,window,delta);
-		self.draw(game,window);
-		None
+		self.next.take().unwrap_or(Transition::Stay)
+	}
+
+	fn interpolate(&mut self, alpha: f32) {
+		for creature in self.creatures.iter_mut() {
+			creature.interpolate(alpha);
+			if creature.player {
+				self.view.set_center( &creature.interpolated_position(alpha) );
+			}
+		}
+	}
+
+	fn draw(&self, game : &mut Game, window : &mut RenderWindow) {
+		self.render(game,window);
 	}
 }
 
@@ -896,6 +1562,19 @@ impl Screen for GameplayScreen {
 
 struct TileData {
 	pub sprites: Vec<Sprite>,
+	pub door_sprite: Option<Sprite>,
+	/// floor feature from res/dat/tiles.json, e.g. "water"
+	pub terrain: Option<String>,
+	/// base movement cost from the tile's terrain
+	pub cost: f32,
+	/// the terrain's color, multiplied into the sprites
+	pub tint: Color,
+	pub hazard: Option<HazardInfo>,
+	/// hazard that hasn't been found yet
+	pub hidden: bool,
+	/// autotiled wall pieces: the wall itself while the tile looks like
+	/// one, otherwise the edges of the walls beside it
+	pub wall_sprites: Vec<Sprite>,
 	pub bounds: FloatRect,
 	pub tile: Tile,
 	pub seen: bool,
@@ -904,15 +1583,55 @@ struct TileData {
 
 impl TileData {
 	pub fn new(bounds: &FloatRect, tile: &Tile) -> TileData {
-		TileData { sprites: Vec::new(), bounds: bounds.clone(),
+		TileData { sprites: Vec::new(), door_sprite: None, terrain: None, cost: 1.0, tint: Color::white(),
+			hazard: None, hidden: false, wall_sprites: Vec::new(), bounds: bounds.clone(),
 			tile: tile.clone(), seen: false, visible: false }
 	}
+	/// can something walk through it right now?
 	pub fn is_passable(&self) -> bool {
-		self.tile.t != Wall
+		match self.tile.t {
+			Wall => false,
+			Door(state) => !state.blocks(),
+			_ => true
+		}
+	}
+	/// should it be in the pathfinding graph? closed doors can be opened
+	pub fn is_navigable(&self) -> bool {
+		match self.tile.t {
+			Wall | Door(Locked) | Door(Secret) => false,
+			_ => true
+		}
 	}
 	pub fn is_clear(&self) -> bool {
 		self.is_passable()
 	}
+	pub fn looks_like_wall(&self) -> bool {
+		self.tile.t == Wall || self.door_state() == Some(Secret)
+	}
+	/// terrain as the player sees it; hidden traps look like floor
+	pub fn visible_terrain(&self) -> Option<&str> {
+		if self.hidden { None } else { self.terrain.as_ref().map(|name| &name[..]) }
+	}
+	pub fn visible_tint(&self) -> Color {
+		if self.hidden { Color::white() } else { self.tint }
+	}
+	/// terrain and cost for the path graph; known hazards cost extra
+	pub fn path_terrain(&self) -> Option<(&str, f32)> {
+		if self.door_state() == Some(Closed) {
+			return Some((CLOSED_DOOR, 1.0));
+		}
+		match (self.visible_terrain(), &self.hazard) {
+			(None, _) => None,
+			(Some(name), &Some(ref h)) => Some((name, self.cost * h.avoid)),
+			(Some(name), &None) => Some((name, self.cost)),
+		}
+	}
+	pub fn door_state(&self) -> Option<DoorState> {
+		match self.tile.t {
+			Door(state) => Some(state),
+			_ => None
+		}
+	}
 }
 
 ///////////////// utility stuff
@@ -920,6 +1639,15 @@ impl TileData {
 
 impl GameplayScreen {
 
+	// the graph, to change the node at `coords`; copied first if a solver
+	// is still using it, and noted so its paths can be checked against it
+	fn graph_at(&mut self, coords: (isize,isize)) -> &mut Graph {
+		if self.solvers.iter().any(|s| s.get_problem_count() > 0) {
+			self.graph_edits.push((self.graph.version(),coords));
+		}
+		Arc::make_mut(&mut self.graph)
+	}
+
 	// closure for non-diagonal connections
 	fn connect_direct (&mut self, x: isize, y: isize, offset: (isize,isize)) -> bool {
 		let (ox,oy) = offset;
@@ -928,10 +1656,12 @@ impl GameplayScreen {
 		let idx2_opt = self.tile_idx_from_coords( (x2, y2) );
 		match idx2_opt {
 			None => false,
-			Some(idx2) => match self.tiles[idx2].is_passable() {
+			Some(idx2) => match self.tiles[idx2].is_navigable() {
 				false => false,
-				true => self.graph.write().ok().unwrap()
-					.connect_nodes_at(x,y,x2,y2)
+				true => {
+					self.graph_at((x2,y2));
+					self.graph_at((x,y)).connect_nodes_at(x,y,x2,y2)
+				}
 			}
 		}
 	}
@@ -943,8 +1673,8 @@ impl GameplayScreen {
 		let check2_idx_opt = self.tile_idx_from_coords(check2);
 		match (check1_idx_opt,check2_idx_opt) {
 			(Some(check1_idx),Some(check2_idx)) => match (
-				self.tiles[check1_idx].is_passable(),
-				self.tiles[check2_idx].is_passable()
+				self.tiles[check1_idx].is_navigable(),
+				self.tiles[check2_idx].is_navigable()
 			) {
 				(true,true) => self.connect_direct(x,y,offset),
 				(_,_) => false
@@ -953,32 +1683,120 @@ impl GameplayScreen {
 		}
 	}
 
-	fn add_wall_check(&mut self, tile_data: &mut TileData, offset: (isize,isize), wall: &Sprite,
-			wall_off: f32) -> bool {
-		if offset == (0,0) || tile_data.tile.t == Wall { return false; }
-		let (ox,oy) = offset;
-		let (x,y) = (tile_data.tile.x,tile_data.tile.y);
-		match self.dungeon.get_tile_type(x+ox,y+oy) {
-			Some(t) => match t {
-				Wall => {
-					let mut spr = wall.clone();
-					let half = self.tile_sizef / 2.0;
-					spr.set_origin2f(half,half);
-					spr.set_position2f(
-						x as f32 * self.tile_sizef,
-						y as f32 * self.tile_sizef
-					);
-					spr.move2f(
-						wall_off * ox as f32,
-						wall_off * oy as f32
-					);
-					tile_data.sprites.push(spr);
-					true
+	fn door_state(&self, coords: (isize,isize)) -> Option<DoorState> {
+		self.tile_data_from_coords(coords).and_then(|t| t.door_state())
+	}
+
+	fn set_door_state(&mut self, coords: (isize,isize), state: DoorState) -> bool {
+		let idx = match self.tile_idx_from_coords(coords) {
+			Some(idx) if self.tiles[idx].door_state().is_some() => idx,
+			_ => return false
+		};
+		let was_navigable = self.tiles[idx].is_navigable();
+		let was_closed = self.tiles[idx].door_state() == Some(Closed);
+		self.tiles[idx].tile.t = Door(state);
+		let (x,y) = coords;
+		self.sounds.push(("door", Vector2f::new(x as f32 * self.tile_sizef, y as f32 * self.tile_sizef)));
+		if (state == Closed) != was_closed {
+			let terrain = self.tiles[idx].path_terrain();
+			self.graph_at((x,y)).set_terrain_at(x,y,terrain);
+		}
+		if self.tiles[idx].is_navigable() != was_navigable {
+			self.refresh_graph_around(coords);
+			// paths already handed out predate the change
+			for creature in self.creatures.iter_mut() {
+				creature.path_target = None;
+			}
+		}
+		true
+	}
+
+	// open or close the first door next to the hero
+	fn toggle_adjacent_door(&mut self) {
+		let hero = match self.creatures.iter().position(|c| c.player) {
+			Some(hero) => hero,
+			None => return
+		};
+		let (hx,hy) = self.tile_coords_from_creature(&self.creatures[hero]);
+		for &(ox,oy) in [(0,-1),(1,0),(0,1),(-1,0)].iter() {
+			let coords = (hx+ox,hy+oy);
+			match self.door_state(coords) {
+				Some(Open) => {
+					// don't shut it on anyone
+					let blocked = self.creatures.iter()
+						.any(|c| self.tile_coords_from_creature(c) == coords);
+					if !blocked {
+						self.set_door_state(coords,Closed);
+						return;
+					}
 				}
-				_ => false
-			},
-			_ => false
+				Some(Closed) => {
+					self.set_door_state(coords,Open);
+					return;
+				}
+				Some(Locked) if self.stats.keys > 0 => {
+					self.stats.keys -= 1;
+					self.set_door_state(coords,Open);
+					self.hud.message("You unlock the door.");
+					return;
+				}
+				Some(Locked) => self.hud.message("The door is locked."),
+				Some(Secret) | None => {}
+			}
+		}
+	}
+
+	/// Re-links the graph around a tile whose passability changed. Only the
+	/// 3x3 block can be affected: diagonals depend on the tiles beside them.
+	fn refresh_graph_around(&mut self, coords: (isize,isize)) {
+		let (cx,cy) = coords;
+		for y in (cy-1)..(cy+2) {
+			for x in (cx-1)..(cx+2) {
+				self.graph_at((x,y)).disconnect_node_at(x,y);
+			}
+		}
+		for y in (cy-1)..(cy+2) {
+			for x in (cx-1)..(cx+2) {
+				let navigable = self.tile_data_from_coords((x,y)).map_or(false, |t| t.is_navigable());
+				if !navigable { continue; }
+				for &(ox,oy) in [(1,0),(-1,0),(0,1),(0,-1)].iter() {
+					self.connect_direct(x,y,(ox,oy));
+				}
+				for &(ox,oy) in [(1,1),(-1,1),(1,-1),(-1,-1)].iter() {
+					self.connect_diag(x,y,(ox,oy),(x+ox,y),(x,y+oy));
+				}
+			}
 		}
 	}
 
-}
\ No newline at end of file
+	// wall pieces for the tile, picked by which of its neighbors are walls:
+	// a wall's own art, or the bordered edges a floor tile shows toward them
+	fn autotile_sprites(&self, coords: (isize,isize)) -> Vec<Sprite> {
+		let looks_like_wall = |c: (isize,isize)| self.tile_data_from_coords(c).map_or(true, |t| t.looks_like_wall());
+		let (x,y) = coords;
+		let mask = autotile::neighbor_mask(x, y, |nx,ny| looks_like_wall((nx,ny)));
+		let pieces = if looks_like_wall(coords) {
+			autotile::wall_variants(&self.wall_set, mask).into_iter().map(|pos| (pos,(0,0))).collect()
+		} else {
+			autotile::edge_variants(&self.wall_set, mask)
+		};
+		let t_sz = self.tile_size;
+		let nudge = autotile::EDGE_NUDGE as f32;
+		pieces.iter().map(|&((sx,sy),(ox,oy))| {
+			let (tx,ty) = get_sprite_coords(sx as usize,sy as usize,t_sz,t_sz);
+			let mut spr = Sprite::new_with_texture(self.texture.clone()).expect("Failed to create wall sprite");
+			spr.set_texture_rect(&IntRect{ left: tx as i32, top: ty as i32, width: t_sz as i32, height: t_sz as i32 });
+			spr.set_origin2f(t_sz as f32/2.0, t_sz as f32/2.0);
+			spr.set_position2f(x as f32 * self.tile_sizef + ox as f32 * nudge,
+				y as f32 * self.tile_sizef + oy as f32 * nudge);
+			spr
+		}).collect()
+	}
+
+}
+
+fn modulate(a: &Color, b: &Color) -> Color {
+	let mul = |x: u8, y: u8| ((x as u16 * y as u16) / 255) as u8;
+	Color{red: mul(a.red,b.red), green: mul(a.green,b.green),
+		blue: mul(a.blue,b.blue), alpha: mul(a.alpha,b.alpha)}
+}
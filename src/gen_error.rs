@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why a dungeon generator gave up.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenError {
+    /// The parameters can never produce a valid dungeon.
+    ImpossibleParams(String),
+    /// A tile name in the parameters isn't in the tile map.
+    UnknownTile(String),
+    /// Placement kept failing until the attempt budget ran out.
+    RetryBudgetExhausted { placed: usize, wanted: usize, attempts: usize },
+    /// The layout was built but couldn't be finished (stairs, etc.).
+    InvalidLayout(String),
+}
+
+pub use self::GenError::*;
+
+pub type GenResult<T> = Result<T, GenError>;
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenError::ImpossibleParams(ref why) =>
+                write!(f, "impossible dungeon parameters: {}", why),
+            GenError::UnknownTile(ref name) =>
+                write!(f, "unknown tile name `{}`", name),
+            GenError::RetryBudgetExhausted { placed, wanted, attempts } =>
+                write!(f, "placed only {} of {} rooms after {} attempts",
+                        placed, wanted, attempts),
+            GenError::InvalidLayout(ref why) =>
+                write!(f, "invalid layout: {}", why),
+        }
+    }
+}
+
+impl Error for GenError {
+    fn description(&self) -> &str {
+        match *self {
+            GenError::ImpossibleParams(_) => "impossible dungeon parameters",
+            GenError::UnknownTile(_) => "unknown tile name",
+            GenError::RetryBudgetExhausted { .. } => "retry budget exhausted",
+            GenError::InvalidLayout(_) => "invalid layout",
+        }
+    }
+}
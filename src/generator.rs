@@ -1,15 +1,25 @@
 // TODO this is ugly as sin... refactor!
 
 use std::vec::Vec;
-use rand::{Rng,XorShiftRng,SeedableRng};
+use std::fs::File;
+use std::io::Read;
+use rand::Rng;
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self,Json};
+use std::cmp::{min,max};
 use util::map_range_f32;
+use seed::{Seed,Stream};
+use world::{TileMap,TILES_FILE,load_tile_map};
+use gen_error::{GenResult,ImpossibleParams,RetryBudgetExhausted,InvalidLayout};
 
 #[derive(Clone,Copy)]
 pub struct Tile {
 	pub x: isize,
 	pub y: isize,
 	pub t: TileType,
-	pub e: Option<Entity>
+	pub e: Option<Entity>,
+	/// floor feature, as an index into the dungeon's `terrain` names
+	pub terrain: Option<usize>,
 }
 
 #[derive(Clone,Copy)]
@@ -25,7 +35,7 @@ pub use self::Entity::*;
 pub enum TileType {
 	Floor,
 	Wall,
-	Door,
+	Door(DoorState),
 	Corridor,
 	StairsUp,
 	StairsDown,
@@ -33,6 +43,22 @@ pub enum TileType {
 }
 pub use self::TileType::*;
 
+/// closed, locked and secret doors block movement and sight
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum DoorState {
+	Closed,
+	Open,
+	Locked,
+	/// looks like a wall until someone finds it
+	Secret,
+}
+
+impl DoorState {
+	pub fn blocks(&self) -> bool {
+		*self != DoorState::Open
+	}
+}
+
 #[derive(Clone)]
 pub struct Dungeon {
 	pub width: isize,
@@ -41,9 +67,12 @@ pub struct Dungeon {
 	path_length: usize,
 	pub start_coords: (isize,isize),
 	pub end_coords: (isize,isize),
+	pub rooms: Vec<Room>,
+	/// names from res/dat/tiles.json that tiles' `terrain` points into
+	pub terrain: Vec<String>,
 }
 
-#[derive(Clone,Copy)]
+#[derive(Clone,Copy,RustcEncodable,RustcDecodable)]
 pub struct DungeonParams {
 	room_count: isize,
 	room_size_min: isize,
@@ -56,9 +85,32 @@ pub struct DungeonParams {
 	hall_monsters_max: isize,
 	hall_chance: f32,
 	map_width: isize,
-	map_height: isize
+	map_height: isize,
+	max_attempts: usize,
+	floor: u32,
 }
 
+/// Floor features the generator scatters: every tile in res/dat/tiles.json
+/// with a `min_floor`. Sorted by name, so a seed gives the same dungeon
+/// however the file is laid out.
+#[derive(Clone,Debug,Default)]
+pub struct Features {
+	/// plain terrain like water, with the first floor each can show up on
+	pub terrain: Vec<(String,u32)>,
+	/// traps and the like, the same way
+	pub hazards: Vec<(String,u32)>,
+}
+
+/// chance for a room to get a patch of terrain
+static TERRAIN_ROOM_CHANCE: f32 = 0.3;
+
+/// chance for a dead-end room to become a hidden vault
+static SECRET_ROOM_CHANCE: f32 = 0.25;
+static VAULT_TREASURE_MAX: isize = 3;
+/// chance for a dead-end room that isn't a vault to be locked, with its
+/// key left in the starting room
+static LOCKED_ROOM_CHANCE: f32 = 0.25;
+
 /********************/
 /* public functions */
 /********************/
@@ -74,14 +126,21 @@ impl Dungeon {
 					let i = i as isize;
 					let x: isize = i % w;
 					let y: isize = i / w;
-					Tile { x: x, y: y, t: Wall, e: None }
+					Tile { x: x, y: y, t: Wall, e: None, terrain: None }
 				}).collect(),
 			path_length: 0,
 			start_coords: (0,0),
 			end_coords: (0,0),
+			rooms: Vec::new(),
+			terrain: Vec::new(),
 		}
 	}
 
+	/// the name of the tile's floor feature, e.g. "water"
+	pub fn terrain_name(&self, tile: &Tile) -> Option<&str> {
+		tile.terrain.and_then(|i| self.terrain.get(i)).map(|name| &name[..])
+	}
+
 	pub fn get_tile<'a>(&'a self, x: isize, y: isize) -> Option<&'a Tile> {
 
 		let idx = x+y*self.width;
@@ -109,7 +168,15 @@ impl Dungeon {
 	}
 
 	pub fn print(&self) {
+		print!("{}",self.to_ascii());
+		println!("Size: {}x{}",self.width,self.height);
+		println!("Path length: {}",self.path_length);
+	}
+
+	/// one character per tile, one line per row
+	pub fn to_ascii(&self) -> String {
 		let dungeon = self;
+		let mut out = String::new();
 		for y in 0..dungeon.height {
 			for x in 0..dungeon.width {
 				let t: char = match dungeon.get_tile(x,y) {
@@ -125,19 +192,18 @@ impl Dungeon {
 							Floor => ' ',
 							Wall => '#',
 							Corridor => ' ',
-							Door => '|',
+							Door(_) => '|',
 							StairsUp => '^',
 							StairsDown => 'V',
 							_ => '?'
 						}
 					}
 				};
-				print!("{}",t);
+				out.push(t);
 			}
-			print!("\n");
+			out.push('\n');
 		}
-		println!("Size: {}x{}",dungeon.width,dungeon.height);
-		println!("Path length: {}",dungeon.path_length);
+		out
 	}
 
 	pub fn width(&self) -> isize {
@@ -148,6 +214,11 @@ impl Dungeon {
 		self.height
 	}
 
+	/// number of rooms and halls between the up and down stairs
+	pub fn path_length(&self) -> usize {
+		self.path_length
+	}
+
 }
 
 impl Default for DungeonParams {
@@ -164,48 +235,169 @@ impl Default for DungeonParams {
 			hall_monsters_max: 2,
 			hall_chance: 0.25,
 			map_width: 250,
-			map_height: 250
+			map_height: 250,
+			max_attempts: 100000,
+			floor: 0,
 		}
 	}
 }
 
+impl DungeonParams {
+
+	/// Reads parameters from a JSON object; anything it leaves out keeps
+	/// its default. Doesn't `check()` them.
+	pub fn load(path: &str) -> Result<DungeonParams, String> {
+		let mut s = String::new();
+		try!(File::open(path).and_then(|mut f| f.read_to_string(&mut s))
+				.map_err(|e| format!("{}: {}", path, e)));
+		let given = try!(Json::from_str(&s).map_err(|e| format!("{}: {}", path, e)));
+		let given = try!(given.as_object().cloned()
+				.ok_or(format!("{}: expected an object", path)));
+
+		// lay the file over the defaults and decode the result
+		let mut merged = Json::from_str(&json::encode(&DungeonParams::default()).unwrap()).unwrap();
+		{
+			let fields = merged.as_object_mut().unwrap();
+			for (key, value) in given.into_iter() {
+				if !fields.contains_key(&key) {
+					return Err(format!("{}: unknown parameter `{}`", path, key));
+				}
+				fields.insert(key, value);
+			}
+		}
+		DungeonParams::decode(&mut json::Decoder::new(merged))
+			.map_err(|e| format!("{}: {}", path, e))
+	}
+
+	/// how many room placements to try before giving up
+	pub fn max_attempts(mut self, attempts: usize) -> Self {
+		self.max_attempts = attempts;
+		self
+	}
+
+	/// which floor of the run this is; each floor gets its own RNG streams
+	pub fn floor(mut self, floor: u32) -> Self {
+		self.floor = floor;
+		self
+	}
+
+	/// catch parameters that could never finish generating
+	pub fn check(&self) -> GenResult<()> {
+		let fail = |why: String| Err(ImpossibleParams(why));
+		if self.room_count < 1 {
+			return fail(format!("room_count must be at least 1, got {}",self.room_count));
+		}
+		if self.room_size_min < 1 || self.room_size_min > self.room_size_max {
+			return fail(format!("bad room size range {}..{}",self.room_size_min,self.room_size_max));
+		}
+		if self.hall_width_min < 1 || self.hall_width_min > self.hall_width_max {
+			return fail(format!("bad hall width range {}..{}",self.hall_width_min,self.hall_width_max));
+		}
+		if self.hall_length_min < 1 || self.hall_length_min > self.hall_length_max {
+			return fail(format!("bad hall length range {}..{}",self.hall_length_min,self.hall_length_max));
+		}
+		if self.room_monsters_max < 0 || self.hall_monsters_max < 0 {
+			return fail("monster limits can't be negative".into());
+		}
+		// rooms only attach to halls, so we need both
+		if self.room_count > 1 && !(self.hall_chance > 0.0 && self.hall_chance < 1.0) {
+			return fail(format!("hall_chance must be between 0 and 1, got {}",self.hall_chance));
+		}
+		// the first room needs a one-tile border on every side
+		let biggest = max(self.room_size_max,self.hall_length_max);
+		if self.map_width < biggest + 3 || self.map_height < biggest + 3 {
+			return fail(format!("{}x{} map can't fit a {}-tile room",
+				self.map_width,self.map_height,biggest));
+		}
+		// every room needs at least its own area plus a wall
+		let room_area = (self.room_size_min+1)*(self.room_size_min+1);
+		let map_area = (self.map_width-2)*(self.map_height-2);
+		if self.room_count*room_area > map_area {
+			return fail(format!("{} rooms of at least {}x{} can't fit in a {}x{} map",
+				self.room_count,self.room_size_min,self.room_size_min,
+				self.map_width,self.map_height));
+		}
+		Ok(())
+	}
 
-pub fn generate_default(seed: u32) -> Dungeon {
-	generate(seed,&DungeonParams::default())
+}
+
+
+impl Features {
+
+	pub fn from_tile_map(map: &TileMap) -> Features {
+		let mut features = Features::default();
+		for info in map.values() {
+			match (info.min_floor, &info.hazard) {
+				(None, _) => {}
+				(Some(floor), &None) => features.terrain.push((info.name.clone(),floor)),
+				(Some(floor), &Some(_)) => features.hazards.push((info.name.clone(),floor)),
+			}
+		}
+		features.terrain.sort();
+		features.hazards.sort();
+		features
+	}
+
+	/// From res/dat/tiles.json, read once at startup and passed to
+	/// `generate_with`.
+	pub fn load_default() -> Result<Features,String> {
+		load_tile_map(TILES_FILE)
+			.map(|map| Features::from_tile_map(&map))
+			.map_err(|e| format!("Couldn't load terrain and hazards: {}",e))
+	}
+
+}
+
+pub fn generate_default(seed: &Seed, features: &Features) -> GenResult<Dungeon> {
+	generate_with(seed,&DungeonParams::default(),features)
 }
 
 // TODO monsters and treasure
 // TODO stair key in second-furthest room (not adjacent to exit)
-pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
+pub fn generate_with(seed: &Seed, params: &DungeonParams, features: &Features) -> GenResult<Dungeon> {
+
+	try!(params.check());
 
-	let seed_array = [seed+1,seed/2,seed/4,seed/8];
-	let mut rng: XorShiftRng = SeedableRng::from_seed(seed_array);
+	let mut rng = seed.rng(params.floor,Stream::Layout);
 
 	// let mut rng = task_rng();
 	let mut d = Dungeon::empty(params.map_width,params.map_height);
 
 	let mut rooms: Vec<Room> = Vec::new();
 	let mut actual_rooms: usize = 0;
+	let mut attempts: usize = 0;
 
 	let mut neighbors: Vec<Vec<usize>> = Vec::new();
+	// the door each room was attached through, if any
+	let mut doors: Vec<Option<(isize,isize)>> = Vec::new();
 
 	while actual_rooms < params.room_count as usize {
 
+		attempts += 1;
+		if attempts > params.max_attempts {
+			return Err(RetryBudgetExhausted {
+				placed: actual_rooms,
+				wanted: params.room_count as usize,
+				attempts: params.max_attempts,
+			});
+		}
+
 		// don't start with a hall
 		let is_first = rooms.len() == 0;
 		let is_hall = !is_first && rng.gen_range(0f32,1f32) < params.hall_chance;
 
 		// declare room fields
-		let mut x;
-		let mut y;
-		let mut w;
-		let mut h;
+		let x;
+		let y;
+		let w;
+		let h;
 		let mut c_x: Option<isize> = None;
 		let mut c_y: Option<isize> = None;
 		let mut add_door = true;
 
 		// are we adding
-		let mut exist_idx = -1;
+		let mut exist_idx = None;
 
 		if is_hall {
 			let len = rng.gen_range(params.hall_length_min,params.hall_length_max+1);
@@ -226,9 +418,8 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 			x = rng.gen_range(1, d.width - 1 - w);
 			y = rng.gen_range(1, d.height - 1 - h);
 		} else {
-			exist_idx = rng.gen_range(0,rooms.len() as isize);
-			let existing = rooms.get(exist_idx as usize)
-					.expect("nooooooo");
+			let idx = rng.gen_range(0,rooms.len() as isize) as usize;
+			let existing = rooms[idx];
 
 			// TODO is this necessary?
 			// don't directly connect rooms
@@ -242,26 +433,17 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 			}
 
 			// pick cardinal direction to attch room
+			// (0 = north, 1 = east, 2 = south, 3 = west)
 			let direction = rng.gen_range(0,4);
 			// pick x point
 			let connect_x = match direction {
 				0|2 => rng.gen_range(existing.x,existing.x+existing.w),
-				1|3 => match rng.gen_range(0,2) {
-					0 => existing.x-1,
-					1 => existing.x + existing.w,
-					_ => panic!("A")
-				},
-				_ => panic!("B")
+				_ => if rng.gen_range(0,2) == 0 { existing.x-1 } else { existing.x + existing.w },
 			};
 			// pick y point
 			let connect_y = match direction {
 				1|3 => rng.gen_range(existing.y,existing.y+existing.h),
-				0|2 => match rng.gen_range(0,2) {
-					0 => existing.y-1,
-					1 => existing.y + existing.h,
-					_ => panic!("C")
-				},
-				_ => panic!("D")
+				_ => if rng.gen_range(0,2) == 0 { existing.y-1 } else { existing.y + existing.h },
 			};
 			// now that we have a connector point,
 			// figure out where we want to put the new room
@@ -271,32 +453,35 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 				// what a bug...
 				0|2 => rng.gen_range(connect_x-(w-1),connect_x+1),
 				1 => connect_x+1,
-				3 => connect_x-w,
-				_ => panic!("E")
+				_ => connect_x-w,
 			};
 			y = match direction {
 				// same as above
 				1|3 => rng.gen_range(connect_y-(h-1),connect_y+1),
 				2 => connect_y+1,
-				0 => connect_y-h,
-				_ => panic!("F")
+				_ => connect_y-h,
 			};
 			c_x = Some(connect_x);
 			c_y = Some(connect_y);
+			exist_idx = Some(idx);
 		}
 
-		let room = Room { x: x, y: y, w: w, h: h, hall: is_hall };
+		let room = Room { x: x, y: y, w: w, h: h, hall: is_hall, secret: false };
 
 		let fill_type = if is_hall { Corridor } else { Floor };
 		if d.fill_room(&room,fill_type) {
 			rooms.push(room);
 
 			// add door unless both are halls
-			let door_tile = if add_door { Door } else { fill_type };
+			let door_tile = if add_door { Door(DoorState::Closed) } else { fill_type };
 			match (c_x,c_y) {
 				(Some(x),Some(y)) => { d.set_tile(x,y,door_tile); }
 				_ => {}
 			}
+			doors.push(match (c_x,c_y) {
+				(Some(x),Some(y)) if add_door => Some((x,y)),
+				_ => None
+			});
 
 			// update room count if not hall
 			if !room.hall {
@@ -305,24 +490,21 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 
 			// update adjacency list
 			neighbors.push(Vec::new());
-			if exist_idx != -1 {
+			if let Some(exist_idx) = exist_idx {
 				let new_idx = rooms.len()-1;
-				neighbors[exist_idx as usize].push(new_idx as usize);
-				neighbors[new_idx].push(exist_idx as usize);
+				neighbors[exist_idx].push(new_idx);
+				neighbors[new_idx].push(exist_idx);
 			}
 		} // else try again :-(
 	}
 
 
-	// let's do this differently
-	let mut start_idx_opt: Option<usize> = None;
-
-	while start_idx_opt.is_none() || rooms[ start_idx_opt.expect("Shouldn't be here lawl") ].hall {
-		start_idx_opt = Some( rng.gen_range(0, rooms.len() as isize) as usize );
+	// start in a random room (the first room is never a hall, so this ends)
+	let mut start_idx = rng.gen_range(0, rooms.len() as isize) as usize;
+	while rooms[start_idx].hall {
+		start_idx = rng.gen_range(0, rooms.len() as isize) as usize;
 	}
 
-	let start_idx = start_idx_opt.expect("Okay cool good wut");
-
 	let mut distances: Vec<Option<usize>> = Vec::new();
 	for _ in 0..rooms.len() { distances.push(None) }
 	let mut queue: Vec<usize> = Vec::new();
@@ -333,18 +515,16 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 
 	while queue.len() > 0 {
 		let current_idx = queue.remove(0);
-		let current_dist = distances[current_idx].expect("Distance should be set");
-		let adjacent = neighbors.get(current_idx).expect("mr skeltal");
-		for neighbor in adjacent.iter() {
+		let current_dist = distances[current_idx].unwrap_or(0);
+		for &neighbor in neighbors[current_idx].iter() {
 
-			if visited_idx.contains( neighbor ) { continue; }
+			if visited_idx.contains( &neighbor ) { continue; }
 
-			let old_dist = distances[neighbor.clone() as usize].clone();
 			let new_dist = current_dist + 1;
-			if old_dist.is_none() || old_dist.expect("wat") > new_dist {
-				distances[neighbor.clone() as usize] = Some(new_dist);
+			if distances[neighbor].map_or(true, |old_dist| old_dist > new_dist) {
+				distances[neighbor] = Some(new_dist);
 			}
-			queue.push(neighbor.clone() as usize);
+			queue.push(neighbor);
 		}
 
 		visited_idx.push(current_idx);
@@ -355,28 +535,25 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 	// }
 
 	// find room with furthest distance
-	let mut furthest_idx: Option<usize> = None;
-	let mut furthest_dist: Option<usize> = None;
+	// (every room was attached to an existing one, so all are reachable)
+	let mut furthest_idx = start_idx;
+	let mut furthest_dist = 0;
 	for i in 0..rooms.len() {
-		let room = rooms[i].clone();
-		if room.hall { continue; }
-		let dist: usize = distances[i]
-				.expect("Distance somehow isn't set");
-		if furthest_dist.is_none() || furthest_dist.expect("O_o") < dist {
-			furthest_dist = Some(dist);
-			furthest_idx = Some(i);
+		if rooms[i].hall { continue; }
+		let dist = distances[i].unwrap_or(0);
+		if furthest_dist < dist {
+			furthest_dist = dist;
+			furthest_idx = i;
 		}
 	}
 
-	// println!("Furthest room is room {} with distance {}",furthest_idx.unwrap(),furthest_dist.unwrap());
+	// println!("Furthest room is room {} with distance {}",furthest_idx,furthest_dist);
 
-	let end = rooms.get_mut(furthest_idx.expect("Furthest index not set"))
-			.expect("pls no").clone();
-	d.path_length = furthest_dist.expect("Srsly wat");
+	d.path_length = furthest_dist;
 
 	// put up stairs in start
-	let start_room: &Room = rooms.get(start_idx).expect("uuugh");
-	let end_room: &Room = &end;
+	let start_room = rooms[start_idx];
+	let end_room = rooms[furthest_idx];
 
 	//println!("Start room: {},{}",start_room.x,start_room.y);
 	//println!("End room: {},{}",end_room.x,end_room.y);
@@ -400,10 +577,11 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 	d.end_coords = (end_x,end_y);
 
 	if !set_start || !set_end {
-		panic!("Failed to set start/end ({}/{})",set_start,set_end);
+		return Err(InvalidLayout(format!("failed to set start/end ({}/{})",set_start,set_end)));
 	}
 
 	// now let's add some enemies
+	let mut rng = seed.rng(params.floor,Stream::Monsters);
 	let mut total_monsters = 0;
 	let min_room_area = f32::powf(params.room_size_min as f32, 2.);
 	let max_room_area = f32::powf(params.room_size_max as f32, 2.);
@@ -420,14 +598,18 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 
 		let max_possible = if room.hall { params.hall_monsters_max } else { params.room_monsters_max } as f32;
 		let max_monsters = map_range_f32( area, min_area, max_area, 0.0, max_possible, true ).round() as isize;
-		let monster_count = rng.gen_range(0, max_monsters + 1);
+		// never ask for more monsters than there are tiles
+		let monster_count = min(rng.gen_range(0, max_monsters + 1), room.w * room.h);
 		total_monsters += monster_count;
 
 		let mut placed_monsters = 0;
 		while placed_monsters < monster_count {
 			let x = rng.gen_range(room.x, room.x+room.w);
 			let y = rng.gen_range(room.y, room.y+room.h);
-			let tile = d.get_tile_mut(x,y).expect("This should NOT be out of range");
+			let tile = match d.get_tile_mut(x,y) {
+				Some(tile) => tile,
+				None => return Err(InvalidLayout(format!("room tile ({},{}) out of range",x,y))),
+			};
 			match tile.e {
 				Some(_) => continue,
 				None => {
@@ -437,92 +619,161 @@ pub fn generate(seed: u32, params: &DungeonParams) -> Dungeon {
 			}
 		}
 	}
-	d
-}
 
-/*************/
-/* internals */
-/*************/
-
-impl Dungeon {
-
-	fn fix_coords(&mut self) {
-		for y in 0..self.height {
-			for x in 0..self.width {
-				let idx = x + y*self.width;
-				let tile = self.tiles.get_mut(idx as usize)
-						.expect("Just ... no");
-				tile.x = x;
-				tile.y = y;
-				match tile.t {
-					StairsUp => self.start_coords = (tile.x,tile.y),
-					StairsDown => self.end_coords = (tile.x,tile.y),
+	// patches of terrain in some rooms -- own stream, so layouts don't move
+	// tiles point into one list of names, terrain first and hazards after
+	d.terrain = features.terrain.iter().chain(features.hazards.iter())
+		.map(|&(ref name,_)| name.clone())
+		.collect();
+	let terrain: Vec<usize> = (0..features.terrain.len())
+		.filter(|&i| params.floor >= features.terrain[i].1)
+		.collect();
+	let mut rng = seed.rng(params.floor,Stream::Terrain);
+	for room in rooms.iter().filter(|r| !r.hall) {
+		if terrain.is_empty() { break; }
+		if rng.gen::<f32>() >= TERRAIN_ROOM_CHANCE { continue; }
+		let feature = terrain[rng.gen_range(0,terrain.len())];
+		let w = rng.gen_range(1,room.w+1);
+		let h = rng.gen_range(1,room.h+1);
+		let x0 = rng.gen_range(room.x,room.x+room.w-w+1);
+		let y0 = rng.gen_range(room.y,room.y+room.h-h+1);
+		for y in y0..(y0+h) {
+			for x in x0..(x0+w) {
+				match d.get_tile_mut(x,y) {
+					Some(ref mut tile) if tile.t == Floor => tile.terrain = Some(feature),
 					_ => {}
 				}
 			}
 		}
 	}
 
-	pub fn shrink(&mut self) {
-		loop {
-			if !self.shrink_h(1,0) { break; }
-		}
-		loop {
-			let w = self.width;
-			if !self.shrink_h(w-2,w-1) { break; }
-		}
-		loop {
-			if !self.shrink_v(1,0) { break; }
-		}
-		loop {
-			let h = self.height;
-			if !self.shrink_v(h-2,h-1) { break; }
+	// hazards get more numerous and nastier further down; never in the
+	// starting room, so the first step is a safe one
+	let available: Vec<usize> = (0..features.hazards.len())
+		.filter(|&i| params.floor >= features.hazards[i].1)
+		.map(|i| features.terrain.len() + i)
+		.collect();
+	let max_hazards = min(1 + params.floor as isize, 4);
+	let mut rng = seed.rng(params.floor,Stream::Hazards);
+	for (_,room) in rooms.iter().enumerate().filter(|&(i,r)| !r.hall && i != start_idx) {
+		if available.is_empty() { break; }
+		let count = rng.gen_range(0, max_hazards + 1);
+		for _ in 0..count {
+			let feature = available[rng.gen_range(0,available.len())];
+			let x = rng.gen_range(room.x, room.x+room.w);
+			let y = rng.gen_range(room.y, room.y+room.h);
+			if (x,y) == d.end_coords { continue; }
+			match d.get_tile_mut(x,y) {
+				Some(ref mut tile) if tile.t == Floor && tile.terrain.is_none() && tile.e.is_none() =>
+					tile.terrain = Some(feature),
+				_ => {}
+			}
 		}
-		self.fix_coords();
 	}
 
-	fn shrink_h(&mut self, x_check: isize, x_remove: isize) -> bool {
-
-		for y in 0..self.height
-		{
-			match self.get_tile(x_check,y) {
-				None => return false,
-				Some(tile) => match tile.t {
-					Wall => {}
-					_ => return false
-				}
+	// dead-end rooms sometimes turn into vaults behind secret doors;
+	// they never hold the stairs, so nobody has to find them
+	let mut rng = seed.rng(params.floor,Stream::Loot);
+	for i in 0..rooms.len() {
+		if rooms[i].hall || neighbors[i].len() != 1 || i == start_idx || i == furthest_idx {
+			continue;
+		}
+		let (dx,dy) = match doors[i] {
+			Some(door) => door,
+			None => continue
+		};
+		if rng.gen::<f32>() >= SECRET_ROOM_CHANCE { continue; }
+		d.set_tile(dx,dy,Door(DoorState::Secret));
+		rooms[i].secret = true;
+
+		// monsters may already fill a small room, so pick from what's left
+		let mut spots = d.free_tiles(&rooms[i],features);
+		let treasure = min(rng.gen_range(1, VAULT_TREASURE_MAX + 1), spots.len() as isize);
+		for _ in 0..treasure {
+			let (x,y) = spots.swap_remove(rng.gen_range(0,spots.len()));
+			if let Some(tile) = d.get_tile_mut(x,y) {
+				tile.e = Some(Treasure);
 			}
 		}
+	}
 
-		for y in 0..self.height {
-			let z = self.height - y - 1;
-			let idx = z*self.width+x_remove;
-			self.tiles.remove(idx as usize);
+	// some of the other dead ends are locked; the key is always in the
+	// starting room, so it can't end up behind its own door
+	let mut rng = seed.rng(params.floor,Stream::Locks);
+	for i in 0..rooms.len() {
+		if rooms[i].hall || rooms[i].secret || neighbors[i].len() != 1
+				|| i == start_idx || i == furthest_idx {
+			continue;
+		}
+		let (dx,dy) = match doors[i] {
+			Some(door) => door,
+			None => continue
+		};
+		if rng.gen::<f32>() >= LOCKED_ROOM_CHANCE { continue; }
+		let spots = d.free_tiles(&rooms[start_idx],features);
+		if spots.is_empty() { continue; }
+		let (kx,ky) = spots[rng.gen_range(0,spots.len())];
+		d.set_tile(dx,dy,Door(DoorState::Locked));
+		if let Some(tile) = d.get_tile_mut(kx,ky) {
+			tile.e = Some(Key);
 		}
-		self.width -= 1;
-		true
 	}
 
-	fn shrink_v(&mut self, y_check: isize, y_remove: isize) -> bool {
+	d.rooms = rooms;
 
-		for x in 0..self.width
-		{
-			match self.get_tile(x,y_check) {
-				None => return false,
-				Some(tile) => match tile.t {
-					Wall => {}
-					_ => return false
-				}
+	Ok(d)
+}
+
+/*************/
+/* internals */
+/*************/
+
+impl Dungeon {
+
+	/// Trims the solid wall around the dungeon, leaving a one-tile border.
+	/// Returns the offset that was subtracted from every coordinate.
+	pub fn crop(&mut self) -> (isize,isize) {
+		let mut min_x = self.width;
+		let mut min_y = self.height;
+		let mut max_x = -1;
+		let mut max_y = -1;
+		for tile in self.tiles.iter() {
+			if tile.t == Wall { continue; }
+			min_x = min(min_x,tile.x);
+			min_y = min(min_y,tile.y);
+			max_x = max(max_x,tile.x);
+			max_y = max(max_y,tile.y);
+		}
+		if max_x < 0 { return (0,0); } // nothing but walls
+
+		// keep one wall tile on each side
+		let left = max(min_x-1,0);
+		let top = max(min_y-1,0);
+		let right = min(max_x+1,self.width-1);
+		let bottom = min(max_y+1,self.height-1);
+		let new_w = right-left+1;
+		let new_h = bottom-top+1;
+
+		let mut tiles = Vec::with_capacity((new_w*new_h) as usize);
+		for y in top..(bottom+1) {
+			for x in left..(right+1) {
+				let mut tile = self.tiles[(x+y*self.width) as usize];
+				tile.x -= left;
+				tile.y -= top;
+				tiles.push(tile);
 			}
 		}
 
-		for x in 0..self.width {
-			let x2 = self.width - x - 1;
-			let idx = y_remove*self.width+x2;
-			self.tiles.remove(idx as usize);
+		self.tiles = tiles;
+		self.width = new_w;
+		self.height = new_h;
+		self.start_coords = (self.start_coords.0-left,self.start_coords.1-top);
+		self.end_coords = (self.end_coords.0-left,self.end_coords.1-top);
+		for room in self.rooms.iter_mut() {
+			room.x -= left;
+			room.y -= top;
 		}
-		self.height -= 1;
-		true
+		(left,top)
 	}
 
 	fn get_tile_mut<'a>(&'a mut self, x: isize, y: isize) -> Option<&'a mut Tile> {
@@ -569,6 +820,23 @@ impl Dungeon {
 		}
 	}
 
+	// tiles in the room something can be put down on, stairs excluded, and
+	// hazards too so nobody has to walk into lava for a key
+	fn free_tiles(&self, room: &Room, features: &Features) -> Vec<(isize,isize)> {
+		let mut spots = Vec::new();
+		for y in room.y..(room.y+room.h) {
+			for x in room.x..(room.x+room.w) {
+				match self.get_tile(x,y) {
+					Some(tile) if tile.e.is_none() && (tile.t == Floor || tile.t == Corridor)
+							&& tile.terrain.map_or(true, |i| i < features.terrain.len()) =>
+						spots.push((x,y)),
+					_ => {}
+				}
+			}
+		}
+		spots
+	}
+
 	fn is_room_position_valid(&self, x: isize, y: isize, w: isize, h: isize) -> bool {
 		if x == 0 || y == 0 || w == 0 || h == 0 { return false; }
 
@@ -589,10 +857,12 @@ impl Dungeon {
 }
 
 #[derive(Clone,Copy)]
-struct Room {
-	x: isize,
-	y: isize,
-	w: isize,
-	h: isize,
-	hall: bool
+pub struct Room {
+	pub x: isize,
+	pub y: isize,
+	pub w: isize,
+	pub h: isize,
+	pub hall: bool,
+	/// only reachable through a secret door
+	pub secret: bool,
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::path::Path;
+use util::get_gfx_path;
+use super::{RegistryError, RegistryResult, read_json};
+
+/// Where animation definitions live, relative to the gfx folder.
+pub static ANIMS_DIR: &'static str = "anims/";
+
+pub static LOOP_MODES: [&'static str; 3] = ["loop", "once", "ping_pong"];
+pub static FACINGS: [&'static str; 4] = ["north", "east", "south", "west"];
+pub static ANIM_STATES: [&'static str; 5] = ["idle", "walk", "attack", "hurt", "death"];
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct ClipOptions {
+    /// atlas positions, in tiles
+    pub frames: Vec<(u32, u32)>,
+    /// seconds per frame; a single value applies to every frame
+    pub durations: Vec<f32>,
+    /// one of `LOOP_MODES`, defaults to `loop`
+    pub mode: Option<String>,
+    /// event name to the frames (counted from 0) that fire it
+    pub events: Option<HashMap<String, Vec<usize>>>,
+}
+
+impl ClipOptions {
+    pub fn duration(&self, frame: usize) -> f32 {
+        match self.durations.len() {
+            1 => self.durations[0],
+            _ => self.durations[frame],
+        }
+    }
+
+    pub fn mode(&self) -> &str {
+        self.mode.as_ref().map(|m| &m[..]).unwrap_or("loop")
+    }
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct AnimSetOptions {
+    pub tile: (u32, u32),
+    pub clips: HashMap<String, ClipOptions>,
+    /// facing (one of `FACINGS`) to the clip played while walking that way
+    pub facing: Option<HashMap<String, String>>,
+    /// state (one of `ANIM_STATES`) to a facing-to-clip map like `facing`;
+    /// the facing `any` covers directions that aren't listed
+    pub states: Option<HashMap<String, HashMap<String, String>>>,
+    /// clip to start on, defaults to whatever `south` maps to
+    pub default: Option<String>,
+}
+
+impl AnimSetOptions {
+    /// Loads `res/gfx/anims/<name>.json`.
+    pub fn load(name: &str) -> RegistryResult<AnimSetOptions> {
+        let path = get_gfx_path(ANIMS_DIR) + name + ".json";
+        let opts: AnimSetOptions = try!(read_json(Path::new(&path)));
+        opts.validate().map_err(|why| RegistryError::Invalid(path, why))
+    }
+
+    pub fn validate(self) -> Result<Self, &'static str> {
+        for clip in self.clips.values() {
+            if clip.frames.is_empty() {
+                return Err("Clip `frames` should have at least one frame");
+            }
+            if clip.durations.len() != 1 && clip.durations.len() != clip.frames.len() {
+                return Err("Clip `durations` should have one value, or one per frame");
+            }
+            if clip.durations.iter().any(|&d| d <= 0.0) {
+                return Err("Clip `durations` should all be positive");
+            }
+            if !LOOP_MODES.iter().any(|&m| m == clip.mode()) {
+                return Err("Clip `mode` should be `loop`, `once` or `ping_pong`");
+            }
+            if let Some(ref events) = clip.events {
+                if events.values().any(|frames| frames.iter().any(|&f| f >= clip.frames.len())) {
+                    return Err("Clip `events` should only name frames the clip has");
+                }
+            }
+        }
+        if let Some(ref facing) = self.facing {
+            for (dir, clip) in facing.iter() {
+                if !FACINGS.iter().any(|&f| f == &dir[..]) {
+                    return Err("`facing` keys should be `north`, `east`, `south` or `west`");
+                }
+                if !self.clips.contains_key(clip) {
+                    return Err("`facing` names a clip that isn't defined");
+                }
+            }
+        }
+        if let Some(ref states) = self.states {
+            for (state, facing) in states.iter() {
+                if !ANIM_STATES.iter().any(|&s| s == &state[..]) {
+                    return Err("`states` keys should be `idle`, `walk`, `attack`, `hurt` or `death`");
+                }
+                for (dir, clip) in facing.iter() {
+                    if dir != "any" && !FACINGS.iter().any(|&f| f == &dir[..]) {
+                        return Err("`states` facings should be a direction or `any`");
+                    }
+                    if !self.clips.contains_key(clip) {
+                        return Err("`states` names a clip that isn't defined");
+                    }
+                }
+            }
+        }
+        if self.default_clip().is_none() {
+            return Err("No `default` clip, and `south` isn't mapped in `facing`");
+        }
+        Ok(self)
+    }
+
+    pub fn facing_clip(&self, facing: &str) -> Option<&str> {
+        self.facing.as_ref().and_then(|f| f.get(facing)).map(|c| &c[..])
+    }
+
+    /// Every state's facing-to-clip map, with `facing` standing in for
+    /// `walk` when `states` doesn't define it.
+    pub fn state_clips(&self) -> HashMap<String, HashMap<String, String>> {
+        let mut states = self.states.clone().unwrap_or(HashMap::new());
+        if let Some(ref facing) = self.facing {
+            states.entry("walk".to_string()).or_insert(facing.clone());
+        }
+        states
+    }
+
+    pub fn default_clip(&self) -> Option<&str> {
+        match self.default {
+            Some(ref clip) if self.clips.contains_key(clip) => Some(clip),
+            Some(_) => None,
+            None => self.facing_clip("south"),
+        }
+    }
+}
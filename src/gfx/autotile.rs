@@ -0,0 +1,89 @@
+// Mask-driven wall tiling. Every tile gets an 8-bit mask of which neighbors
+// are walls, and the mask picks sprite variants by name: `blob_<mask>` art
+// for the wall itself, if a tileset has it, and bordered `edge_<side>` /
+// `corner_<side>` pieces for the floor beside it.
+
+use super::SpriteSetOptions;
+
+pub const N: u8 = 1;
+pub const NE: u8 = 2;
+pub const E: u8 = 4;
+pub const SE: u8 = 8;
+pub const S: u8 = 16;
+pub const SW: u8 = 32;
+pub const W: u8 = 64;
+pub const NW: u8 = 128;
+
+/// neighbor offsets in bit order
+pub const OFFSETS: [(isize, isize, u8); 8] = [
+    (0, -1, N), (1, -1, NE), (1, 0, E), (1, 1, SE),
+    (0, 1, S), (-1, 1, SW), (-1, 0, W), (-1, -1, NW),
+];
+
+/// px an edge piece is pushed toward its wall, so the border covers the seam
+pub const EDGE_NUDGE: isize = 1;
+
+/// Which of the tile's eight neighbors are the same kind of tile.
+pub fn neighbor_mask<F>(x: isize, y: isize, same: F) -> u8 where F: Fn(isize, isize) -> bool {
+    OFFSETS.iter()
+        .filter(|&&(ox, oy, _)| same(x + ox, y + oy))
+        .fold(0, |mask, &(_, _, bit)| mask | bit)
+}
+
+/// Drops corners that don't matter: a corner only counts when both edges
+/// next to it are set. This leaves the 47 distinct blob shapes.
+pub fn reduce(mask: u8) -> u8 {
+    let mut out = mask & (N | E | S | W);
+    for &(corner, a, b) in [(NE, N, E), (SE, S, E), (SW, S, W), (NW, N, W)].iter() {
+        if mask & corner != 0 && mask & a != 0 && mask & b != 0 {
+            out |= corner;
+        }
+    }
+    out
+}
+
+/// Name of the variant drawn for a wall with this mask, e.g. `blob_31`.
+pub fn variant_name(mask: u8) -> String {
+    format!("blob_{}", reduce(mask))
+}
+
+/// Sprite positions for a wall tile whose wall neighbors are `mask`: its
+/// `blob_<mask>` variant, or `solid` for a set without blob art. Walls with
+/// neither draw nothing, and the floor around them shows the edges.
+pub fn wall_variants(opts: &SpriteSetOptions, mask: u8) -> Vec<(u32, u32)> {
+    let variants = match opts.variants {
+        Some(ref variants) => variants,
+        None => return Vec::new(),
+    };
+    variants.get(&variant_name(mask))
+        .or_else(|| variants.get("solid"))
+        .cloned().into_iter().collect()
+}
+
+/// Bordered pieces for a floor tile whose wall neighbors are `mask`, each
+/// with the direction of the wall it faces (to nudge it by `EDGE_NUDGE`),
+/// topmost last. Sides get `edge_<side>`; a corner wall with open floor on
+/// both sides of it gets `corner_<side>`, if the set has one.
+pub fn edge_variants(opts: &SpriteSetOptions, mask: u8) -> Vec<((u32, u32), (isize, isize))> {
+    let variants = match opts.variants {
+        Some(ref variants) => variants,
+        None => return Vec::new(),
+    };
+    let mut pieces = Vec::new();
+    for &(bit, name, dir) in [(N, "edge_n", (0, -1)), (E, "edge_e", (1, 0)),
+                              (S, "edge_s", (0, 1)), (W, "edge_w", (-1, 0))].iter() {
+        if mask & bit != 0 {
+            pieces.push((name, dir));
+        }
+    }
+    let corners = [(NE, N, E, "corner_ne", (1, -1)), (SE, S, E, "corner_se", (1, 1)),
+                   (SW, S, W, "corner_sw", (-1, 1)), (NW, N, W, "corner_nw", (-1, -1))];
+    for &(corner, a, b, name, dir) in corners.iter() {
+        if mask & corner != 0 && mask & a == 0 && mask & b == 0 {
+            pieces.push((name, dir));
+        }
+    }
+    pieces.iter()
+        .filter_map(|&(name, dir)| variants.get(name).map(|&pos| (pos, dir)))
+        .collect()
+}
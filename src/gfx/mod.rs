@@ -1,3 +1,8 @@
 mod sprite_data;
+mod registry;
+mod anim_data;
+pub mod autotile;
 
-pub use self::sprite_data::*;
\ No newline at end of file
+pub use self::sprite_data::*;
+pub use self::registry::*;
+pub use self::anim_data::*;
\ No newline at end of file
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use rustc_serialize::{json, Decodable};
+use util::get_gfx_path;
+use super::{SpriteSetOptions, AnimSetOptions, ANIMS_DIR};
+
+/// Where the tile sprite definitions live, relative to the gfx folder.
+pub static TILES_DIR: &'static str = "tiles/";
+
+/// Sprites the dungeon can't be drawn without.
+pub static TILE_SPRITES: [&'static str; 6] =
+    ["floor", "corridor", "door", "stairs_up", "stairs_down", "wall"];
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(String, io::Error),
+    /// not valid JSON, or not a sprite definition
+    Parse(String, String),
+    /// decoded, but `SpriteSetOptions::validate` rejected it
+    Invalid(String, &'static str),
+    /// nothing defines a sprite by this name
+    Missing(String),
+    /// no animation set by this name was loaded
+    MissingAnim(String),
+}
+
+pub type RegistryResult<T> = Result<T, RegistryError>;
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistryError::Io(ref file, ref e) => write!(f, "{}: {}", file, e),
+            RegistryError::Parse(ref file, ref why) => write!(f, "{}: {}", file, why),
+            RegistryError::Invalid(ref file, why) => write!(f, "{}: {}", file, why),
+            RegistryError::Missing(ref name) =>
+                write!(f, "no sprite named `{}` (expected {}{}{}.json)",
+                        name, get_gfx_path(""), TILES_DIR, name),
+            RegistryError::MissingAnim(ref name) =>
+                write!(f, "no animation set named `{}` (expected {}{}{}.json)",
+                        name, get_gfx_path(""), ANIMS_DIR, name),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn description(&self) -> &str {
+        match *self {
+            RegistryError::Io(..) => "couldn't read sprite definition",
+            RegistryError::Parse(..) => "malformed sprite definition",
+            RegistryError::Invalid(..) => "invalid sprite definition",
+            RegistryError::Missing(..) => "missing sprite definition",
+            RegistryError::MissingAnim(..) => "missing animation set",
+        }
+    }
+}
+
+/// Every sprite definition in a folder, by file name without `.json`, plus
+/// whichever animation sets were asked for with `load_anims`.
+#[derive(Clone, Debug, Default)]
+pub struct SpriteRegistry {
+    sprites: HashMap<String, SpriteSetOptions>,
+    anims: HashMap<String, AnimSetOptions>,
+}
+
+impl SpriteRegistry {
+    /// Loads `res/gfx/tiles/*.json`.
+    pub fn load_default() -> RegistryResult<SpriteRegistry> {
+        SpriteRegistry::load_dir(&get_gfx_path(TILES_DIR))
+    }
+
+    pub fn load_dir(dir: &str) -> RegistryResult<SpriteRegistry> {
+        let entries = try!(fs::read_dir(dir).map_err(|e| RegistryError::Io(dir.to_string(), e)));
+        let mut sprites = HashMap::new();
+        for entry in entries {
+            let path = try!(entry.map_err(|e| RegistryError::Io(dir.to_string(), e))).path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") { continue; }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let opts = try!(load_file(&path));
+            sprites.insert(name, opts);
+        }
+        Ok(SpriteRegistry { sprites: sprites, anims: HashMap::new() })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SpriteSetOptions> {
+        self.sprites.get(name)
+    }
+
+    pub fn require(&self, name: &str) -> RegistryResult<&SpriteSetOptions> {
+        self.get(name).ok_or(RegistryError::Missing(name.to_string()))
+    }
+
+    /// Atlas position (in tiles) of the first frame of a sprite.
+    pub fn position(&self, name: &str) -> RegistryResult<(usize, usize)> {
+        self.require(name).map(|o| (o.position[0] as usize, o.position[1] as usize))
+    }
+
+    /// Fails on the first name in `names` that isn't defined.
+    pub fn check(&self, names: &[&str]) -> RegistryResult<()> {
+        for name in names.iter() {
+            try!(self.require(name));
+        }
+        Ok(())
+    }
+
+    /// Loads and validates `res/gfx/anims/<name>.json` for each of `names`,
+    /// failing on the first one that's missing or broken.
+    pub fn load_anims(&mut self, names: &[&str]) -> RegistryResult<()> {
+        for name in names.iter() {
+            let opts = try!(AnimSetOptions::load(name));
+            self.anims.insert(name.to_string(), opts);
+        }
+        Ok(())
+    }
+
+    pub fn anim(&self, name: &str) -> RegistryResult<&AnimSetOptions> {
+        self.anims.get(name).ok_or(RegistryError::MissingAnim(name.to_string()))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.sprites.keys().map(|k| &k[..]).collect();
+        names.sort();
+        names
+    }
+}
+
+fn load_file(path: &Path) -> RegistryResult<SpriteSetOptions> {
+    let opts: SpriteSetOptions = try!(read_json(path));
+    opts.validate().map_err(|why| RegistryError::Invalid(path.display().to_string(), why))
+}
+
+/// Reads and decodes one definition file, naming the file in any error.
+pub fn read_json<T: Decodable>(path: &Path) -> RegistryResult<T> {
+    let file = path.display().to_string();
+    let mut s = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut s))
+            .map_err(|e| RegistryError::Io(file.clone(), e)));
+    json::decode(&s).map_err(|e| RegistryError::Parse(file, format!("{}", e)))
+}
@@ -4,6 +4,9 @@ use std::collections::{HashMap,HashSet};
 #[derive(Clone)]
 pub struct Graph {
 	node_map: HashMap<GraphNode, HashSet<GraphNode>>,
+	// sparse: plain nodes have no terrain and cost 1.0
+	terrain: HashMap<GraphNode, (String, f32)>,
+	version: u64,
 }
 
 // UNDIRECTED graph implementation
@@ -13,13 +16,21 @@ impl Graph {
 	pub fn new() -> Graph {
 		Graph {
 			node_map: HashMap::new(),
+			terrain: HashMap::new(),
+			version: 0,
 		}
 	}
 
+	/// bumped by every change, so results computed on an older copy can be spotted
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+
 	/* dealing with node locations/coordinates */
 
 	pub fn add_node_at(&mut self, x: isize, y: isize) {
 		self.node_map.insert(GraphNode::new(x,y), HashSet::new());
+		self.version += 1;
 	}
 
 	pub fn find_node_at(&self, x: isize, y: isize) -> Option<GraphNode> {
@@ -45,6 +56,34 @@ impl Graph {
 		}
 	}
 
+	pub fn disconnect_node_at(&mut self, x: isize, y: isize) -> bool {
+		self.disconnect_node(&GraphNode::new(x,y))
+	}
+
+	/// Marks a node as named terrain with a base movement cost, or clears it.
+	pub fn set_terrain_at(&mut self, x: isize, y: isize, terrain: Option<(&str, f32)>) {
+		let node = GraphNode::new(x,y);
+		match terrain {
+			None => { self.terrain.remove(&node); }
+			Some((name,cost)) => { self.terrain.insert(node, (name.to_string(), cost)); }
+		}
+		self.version += 1;
+	}
+
+	pub fn get_terrain(&self, node: &GraphNode) -> Option<&str> {
+		self.terrain.get(node).map(|&(ref name,_)| &name[..])
+	}
+
+	/// cost of stepping onto the node, before any per-creature overrides
+	pub fn get_base_cost(&self, node: &GraphNode) -> f32 {
+		self.terrain.get(node).map_or(1.0, |&(_,cost)| cost)
+	}
+
+	/// cheapest base cost anywhere in the graph
+	pub fn get_min_cost(&self) -> f32 {
+		self.terrain.values().fold(1.0, |min, &(_,cost)| if cost < min { cost } else { min })
+	}
+
 	pub fn remove_node_at(&mut self, x: isize, y: isize) -> bool {
 		match self.find_node_at(x,y) {
 			None => false,
@@ -69,22 +108,38 @@ impl Graph {
 	}
 
 	pub fn remove_node(&mut self, node: &GraphNode) -> bool {
+		self.terrain.remove(node);
 		match self.node_map.remove(node) {
 			None => false,
 			Some(neighbors) => {
 				for neighbor in neighbors.iter() {
 					self.node_map.get_mut(neighbor).expect("Invalid neighbor to remove!").remove(node);
 				}
+				self.version += 1;
 				true
 			}
 		}
 	}
 
+	/// removes every edge touching the node, but keeps the node itself
+	pub fn disconnect_node(&mut self, node: &GraphNode) -> bool {
+		let neighbors = match self.node_map.get_mut(node) {
+			None => return false,
+			Some(set) => ::std::mem::replace(set, HashSet::new())
+		};
+		for neighbor in neighbors.iter() {
+			self.node_map.get_mut(neighbor).expect("Invalid neighbor to disconnect!").remove(node);
+		}
+		self.version += 1;
+		true
+	}
+
 	pub fn connect_nodes(&mut self, a: &GraphNode, b: &GraphNode) -> bool {
 		match (self.node_map.contains_key(a),self.node_map.contains_key(b)) {
 			(true,true) => {
 				self.node_map.get_mut(a).expect("ea").insert(b.clone());
 				self.node_map.get_mut(b).expect("ea").insert(a.clone());
+				self.version += 1;
 				true
 			}
 			(_,_) => false
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use sfml::graphics::{RenderTarget,RenderWindow,Font,Color};
+use sfml::graphics::rc::Text;
+use sfml::system::Vector2f;
+
+use util::{get_fnt_path,get_rc_resource};
+
+static HUD_FONT: &'static str = "hud.ttf";
+const HUD_TEXT_SIZE: u32 = 14;
+/// seconds a message stays up
+const MESSAGE_TIME: f32 = 3.0;
+
+/// The HUD font, shared by anything that draws text.
+pub fn load_font() -> Option<Rc<RefCell<Font>>> {
+	Font::new_from_file(&get_fnt_path(HUD_FONT)).map(get_rc_resource)
+}
+
+/// Screen-space text overlay in `res/fnt/hud.ttf`: a status line, and
+/// below it the latest message for a few seconds. If the font goes missing
+/// the HUD draws nothing (the window title still shows the seed) and
+/// messages are printed instead.
+pub struct Hud {
+	text: Option<Text>,
+	message: Option<Text>,
+	// seconds left to show the message
+	message_left: f32,
+}
+
+impl Hud {
+
+	pub fn new(line: &str) -> Hud {
+		let font = load_font();
+		if font.is_none() {
+			println!("Warning: no HUD font at {}",get_fnt_path(HUD_FONT));
+		}
+		let make = |s: &str, y: f32, color: Color| font.clone()
+			.and_then(|f| Text::new_init(s,f,HUD_TEXT_SIZE))
+			.map(|mut t| {
+				t.set_color(&color);
+				t.set_position(&Vector2f::new(4.0,y));
+				t
+			});
+		Hud {
+			text: make(line,4.0,Color::white()),
+			message: make("",4.0 + HUD_TEXT_SIZE as f32 * 1.5,Color::yellow()),
+			message_left: 0.0,
+		}
+	}
+
+	pub fn set_line(&mut self, line: &str) {
+		match self.text {
+			Some(ref mut t) => t.set_string(line),
+			None => {}
+		}
+	}
+
+	/// Shows `text` under the status line, replacing any message already up.
+	pub fn message(&mut self, text: &str) {
+		match self.message {
+			Some(ref mut t) => t.set_string(text),
+			None => println!("{}",text),
+		}
+		self.message_left = MESSAGE_TIME;
+	}
+
+	/// Counts down how long the message has left, once a tick.
+	pub fn update(&mut self, delta: f32) {
+		self.message_left = (self.message_left - delta).max(0.0);
+	}
+
+	/// Draws in window coordinates, so call it after everything else.
+	pub fn draw(&self, window: &mut RenderWindow) {
+		if self.text.is_none() { return; }
+		let view = window.get_default_view();
+		window.set_view(&view);
+		if let Some(ref t) = self.text {
+			window.draw(t);
+		}
+		match self.message {
+			Some(ref t) if self.message_left > 0.0 => window.draw(t),
+			_ => {}
+		}
+	}
+
+}
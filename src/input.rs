@@ -0,0 +1,507 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+
+use rustc_serialize::json;
+
+use sfml::window::keyboard;
+use sfml::window::keyboard::Key;
+use sfml::window::joystick;
+
+/// key bindings, action name to a list of key (or gamepad button) names
+pub static BINDINGS_FILE: &'static str = "./res/dat/bindings.json";
+/// how far (0 to 1) the stick has to move before the hero does
+pub static STICK_DEAD_ZONE: f32 = 0.2;
+// joysticks SFML keeps track of
+static MAX_PADS: u32 = 8;
+// gamepad buttons are named like this plus a number, e.g. `Button0`
+static BUTTON_PREFIX: &'static str = "Button";
+
+/// Something the player can do, whatever key it's on.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+	MoveUp,
+	MoveDown,
+	MoveLeft,
+	MoveRight,
+	Attack,
+	Door,
+	Search,
+	ZoomIn,
+	ZoomOut,
+	Pause,
+	Inventory,
+	Confirm,
+	DebugGraph,
+	DebugLos,
+	DebugAll,
+}
+
+/// names used in the bindings file and input scripts
+pub static ACTIONS: [(&'static str, Action); 15] = [
+	("move_up", Action::MoveUp),
+	("move_down", Action::MoveDown),
+	("move_left", Action::MoveLeft),
+	("move_right", Action::MoveRight),
+	("attack", Action::Attack),
+	("door", Action::Door),
+	("search", Action::Search),
+	("zoom_in", Action::ZoomIn),
+	("zoom_out", Action::ZoomOut),
+	("pause", Action::Pause),
+	("inventory", Action::Inventory),
+	("confirm", Action::Confirm),
+	("debug_graph", Action::DebugGraph),
+	("debug_los", Action::DebugLos),
+	("debug_all", Action::DebugAll),
+];
+
+impl Action {
+	pub fn from_name(name: &str) -> Option<Action> {
+		ACTIONS.iter().find(|&&(n, _)| n == name).map(|&(_, a)| a)
+	}
+
+	pub fn name(&self) -> &'static str {
+		ACTIONS.iter().find(|&&(_, a)| a == *self).map(|&(n, _)| n).unwrap()
+	}
+}
+
+// keys that can be named in the bindings file
+static KEY_NAMES: [(&'static str, Key); 60] = [
+	("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E),
+	("F", Key::F), ("G", Key::G), ("H", Key::H), ("I", Key::I), ("J", Key::J),
+	("K", Key::K), ("L", Key::L), ("M", Key::M), ("N", Key::N), ("O", Key::O),
+	("P", Key::P), ("Q", Key::Q), ("R", Key::R), ("S", Key::S), ("T", Key::T),
+	("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X), ("Y", Key::Y),
+	("Z", Key::Z),
+	("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3),
+	("4", Key::Num4), ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7),
+	("8", Key::Num8), ("9", Key::Num9),
+	("Escape", Key::Escape), ("Space", Key::Space), ("Return", Key::Return),
+	("Tab", Key::Tab), ("BackSpace", Key::BackSpace),
+	("LShift", Key::LShift), ("RShift", Key::RShift),
+	("LControl", Key::LControl), ("RControl", Key::RControl),
+	("LAlt", Key::LAlt), ("RAlt", Key::RAlt),
+	("Comma", Key::Comma), ("Period", Key::Period), ("Slash", Key::Slash),
+	("Equal", Key::Equal), ("Dash", Key::Dash),
+	("Add", Key::Add), ("Subtract", Key::Subtract),
+	("Left", Key::Left), ("Right", Key::Right), ("Up", Key::Up), ("Down", Key::Down),
+	("PageUp", Key::PageUp), ("PageDown", Key::PageDown),
+];
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+	KEY_NAMES.iter().find(|&&(n, _)| n == name).map(|&(_, k)| k)
+}
+
+pub fn key_name(key: Key) -> Option<&'static str> {
+	KEY_NAMES.iter().find(|&&(_, k)| k == key).map(|&(n, _)| n)
+}
+
+// what you get without a bindings file
+static DEFAULT_BINDINGS: [(&'static str, &'static [&'static str]); 15] = [
+	("move_up", &["Up"]),
+	("move_down", &["Down"]),
+	("move_left", &["Left"]),
+	("move_right", &["Right"]),
+	("attack", &["Space", "Button0"]),
+	("door", &["E", "Button2"]),
+	("search", &["S", "Button3"]),
+	("zoom_in", &["Period"]),
+	("zoom_out", &["Comma"]),
+	("pause", &["Escape", "P", "Button7"]),
+	("inventory", &["I", "Button6"]),
+	("confirm", &["Return"]),
+	("debug_graph", &["G"]),
+	("debug_los", &["L"]),
+	("debug_all", &["D"]),
+];
+
+/// Which keys and gamepad buttons do what. A key can be on more than one
+/// action, and an action on more than one key.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+	keys: Vec<(Key, Action)>,
+	buttons: Vec<(u32, Action)>,
+}
+
+impl Bindings {
+	pub fn new() -> Bindings {
+		Bindings { keys: Vec::new(), buttons: Vec::new() }
+	}
+
+	pub fn bind(&mut self, key: Key, action: Action) {
+		if !self.keys.contains(&(key, action)) {
+			self.keys.push((key, action));
+		}
+	}
+
+	pub fn bind_button(&mut self, button: u32, action: Action) {
+		if !self.buttons.contains(&(button, action)) {
+			self.buttons.push((button, action));
+		}
+	}
+
+	/// Binds a key or button by name; false if there's no such thing.
+	pub fn bind_name(&mut self, name: &str, action: Action) -> bool {
+		if let Some(key) = key_from_name(name) {
+			self.bind(key, action);
+			return true;
+		}
+		if name.starts_with(BUTTON_PREFIX) {
+			if let Ok(button) = name[BUTTON_PREFIX.len()..].parse::<u32>() {
+				self.bind_button(button, action);
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Takes every key and button off an action.
+	pub fn clear(&mut self, action: Action) {
+		self.keys.retain(|&(_, a)| a != action);
+		self.buttons.retain(|&(_, a)| a != action);
+	}
+
+	pub fn actions(&self, key: Key) -> Vec<Action> {
+		self.keys.iter().filter(|&&(k, _)| k == key).map(|&(_, a)| a).collect()
+	}
+
+	pub fn keys(&self, action: Action) -> Vec<Key> {
+		self.keys.iter().filter(|&&(_, a)| a == action).map(|&(k, _)| k).collect()
+	}
+
+	pub fn button_actions(&self, button: u32) -> Vec<Action> {
+		self.buttons.iter().filter(|&&(b, _)| b == button).map(|&(_, a)| a).collect()
+	}
+
+	pub fn buttons(&self, action: Action) -> Vec<u32> {
+		self.buttons.iter().filter(|&&(_, a)| a == action).map(|&(b, _)| b).collect()
+	}
+
+	/// Reads a bindings file. Actions it lists get exactly the keys it gives
+	/// them (none, for an empty list); the rest keep their defaults.
+	pub fn load(path: &str) -> Result<Bindings, String> {
+		let mut s = String::new();
+		try!(File::open(path).and_then(|mut f| f.read_to_string(&mut s))
+				.map_err(|e| format!("{}: {}", path, e)));
+		let names: HashMap<String, Vec<String>> = try!(json::decode(&s)
+				.map_err(|e| format!("{}: {}", path, e)));
+		let mut bindings = Bindings::default();
+		for (action_name, key_names) in names.iter() {
+			let action = try!(Action::from_name(action_name)
+					.ok_or(format!("{}: unknown action `{}`", path, action_name)));
+			bindings.clear(action);
+			for key_name in key_names.iter() {
+				if !bindings.bind_name(key_name, action) {
+					return Err(format!("{}: unknown key `{}` for `{}`", path, key_name, action_name));
+				}
+			}
+		}
+		Ok(bindings)
+	}
+
+	/// res/dat/bindings.json; the defaults (with a warning) if it can't be read.
+	pub fn load_default() -> Bindings {
+		match Bindings::load(BINDINGS_FILE) {
+			Ok(bindings) => bindings,
+			Err(e) => {
+				println!("Warning: using default key bindings ({})", e);
+				Bindings::default()
+			}
+		}
+	}
+}
+
+impl Default for Bindings {
+	fn default() -> Bindings {
+		let mut bindings = Bindings::new();
+		for &(action, keys) in DEFAULT_BINDINGS.iter() {
+			for name in keys.iter() {
+				bindings.bind_name(name, Action::from_name(action).unwrap());
+			}
+		}
+		bindings
+	}
+}
+
+/// What happened to an action in an input script.
+#[derive(RustcDecodable, RustcEncodable, Clone, Copy, PartialEq, Debug)]
+pub enum StepKind {
+	/// starts being held
+	Down,
+	/// stops being held
+	Up,
+	/// pressed once, like a key press event
+	Tap,
+	/// the stick moves to `stick`; `action` is ignored
+	Stick,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Clone, PartialEq, Debug)]
+pub struct ScriptStep {
+	pub tick: u32,
+	pub action: String,
+	pub kind: StepKind,
+	/// x and y, -1 to 1
+	pub stick: Option<(f32, f32)>,
+}
+
+/// Input to replay tick by tick instead of reading the keyboard.
+#[derive(RustcDecodable, RustcEncodable, Clone, PartialEq, Debug, Default)]
+pub struct InputScript {
+	pub steps: Vec<ScriptStep>,
+}
+
+impl InputScript {
+	pub fn new() -> InputScript {
+		InputScript { steps: Vec::new() }
+	}
+
+	pub fn step(mut self, tick: u32, action: Action, kind: StepKind) -> InputScript {
+		self.push(tick, action, kind);
+		self
+	}
+
+	pub fn push(&mut self, tick: u32, action: Action, kind: StepKind) {
+		self.steps.push(ScriptStep { tick: tick, action: action.name().to_string(), kind: kind, stick: None });
+	}
+
+	pub fn push_stick(&mut self, tick: u32, x: f32, y: f32) {
+		self.steps.push(ScriptStep { tick: tick, action: "stick".to_string(), kind: StepKind::Stick, stick: Some((x, y)) });
+	}
+
+	/// Puts the steps in tick order, and the same order within a tick
+	/// every time.
+	pub fn sort(&mut self) {
+		self.steps.sort_by(|a, b| (a.tick, &a.action, a.kind as u8).cmp(&(b.tick, &b.action, b.kind as u8)));
+	}
+
+	/// Tick after the last step.
+	pub fn length(&self) -> u32 {
+		self.steps.iter().map(|s| s.tick + 1).max().unwrap_or(0)
+	}
+
+	pub fn load(path: &str) -> Result<InputScript, String> {
+		let mut s = String::new();
+		try!(File::open(path).and_then(|mut f| f.read_to_string(&mut s))
+				.map_err(|e| format!("{}: {}", path, e)));
+		let script: InputScript = try!(json::decode(&s).map_err(|e| format!("{}: {}", path, e)));
+		for step in script.steps.iter() {
+			if step.kind == StepKind::Stick {
+				if step.stick.is_none() {
+					return Err(format!("{}: stick step without a position at tick {}", path, step.tick));
+				}
+			} else if Action::from_name(&step.action).is_none() {
+				return Err(format!("{}: unknown action `{}` at tick {}", path, step.action, step.tick));
+			}
+		}
+		Ok(script)
+	}
+
+	pub fn save(&self, path: &str) -> Result<(), String> {
+		let s = json::as_pretty_json(self).to_string();
+		File::create(path).and_then(|mut f| f.write_all(s.as_bytes()))
+			.map_err(|e| format!("{}: {}", path, e))
+	}
+}
+
+/// Turns keys into actions, tick by tick. Held actions and the stick come
+/// from polling the keyboard and first gamepad (or a script); one-off presses
+/// come from key and button press events.
+pub struct Input {
+	bindings: Bindings,
+	held: HashSet<Action>,
+	// left stick, -1 to 1 each way
+	stick: (f32, f32),
+	pressed: Vec<Action>,
+	tick: u32,
+	// script being played and how far into it we are
+	script: Option<(InputScript, usize)>,
+	// script being recorded and the tick it started on
+	recording: Option<(InputScript, u32)>,
+}
+
+impl Input {
+	pub fn new(bindings: Bindings) -> Input {
+		Input {
+			bindings: bindings,
+			held: HashSet::new(),
+			stick: (0.0, 0.0),
+			pressed: Vec::new(),
+			tick: 0,
+			script: None,
+			recording: None,
+		}
+	}
+
+	pub fn bindings(&self) -> &Bindings {
+		&self.bindings
+	}
+
+	/// Replays a script from the next tick on, ignoring the keyboard until
+	/// it's done.
+	pub fn play(&mut self, mut script: InputScript) {
+		script.sort();
+		for step in script.steps.iter_mut() {
+			step.tick += self.tick;
+		}
+		self.held.clear();
+		self.stick = (0.0, 0.0);
+		self.script = Some((script, 0));
+	}
+
+	pub fn is_playing(&self) -> bool {
+		self.script.is_some()
+	}
+
+	/// Starts keeping every change in held actions and every press, from the
+	/// next tick on, as a script.
+	pub fn record(&mut self) {
+		self.recording = Some((InputScript::new(), self.tick));
+	}
+
+	pub fn take_recording(&mut self) -> Option<InputScript> {
+		self.recording.take().map(|(mut rec, _)| {
+			rec.sort();
+			rec
+		})
+	}
+
+	/// Call at the start of every tick, before asking what's held or pressed.
+	pub fn update(&mut self) {
+		let mut held = self.held.clone();
+		let mut stick = self.stick;
+		let mut taps = Vec::new();
+		let mut done = false;
+		match self.script {
+			Some((ref script, ref mut next)) => {
+				while *next < script.steps.len() && script.steps[*next].tick <= self.tick {
+					let step = &script.steps[*next];
+					let action = Action::from_name(&step.action);
+					match (step.kind, action) {
+						(StepKind::Stick, _) => stick = step.stick.unwrap_or((0.0, 0.0)),
+						(StepKind::Down, Some(action)) => { held.insert(action); }
+						(StepKind::Up, Some(action)) => { held.remove(&action); }
+						(StepKind::Tap, Some(action)) => taps.push(action),
+						_ => {}
+					}
+					*next += 1;
+				}
+				done = *next >= script.steps.len();
+			}
+			None => {
+				held = self.bindings.keys.iter()
+					.filter(|&&(k, _)| keyboard::is_key_pressed(k))
+					.map(|&(_, a)| a)
+					.collect();
+				stick = (0.0, 0.0);
+				if let Some(pad) = first_pad() {
+					held.extend(self.bindings.buttons.iter()
+						.filter(|&&(b, _)| joystick::is_button_pressed(pad, b))
+						.map(|&(_, a)| a));
+					stick = (joystick::get_axis_position(pad, joystick::Axis::X) / 100.0,
+						joystick::get_axis_position(pad, joystick::Axis::Y) / 100.0);
+				}
+			}
+		}
+		if done {
+			self.script = None;
+		}
+		for action in taps.into_iter() {
+			self.press(action);
+		}
+
+		if let Some((ref mut rec, start)) = self.recording {
+			let tick = self.tick - start;
+			for action in held.difference(&self.held) {
+				rec.push(tick, *action, StepKind::Down);
+			}
+			for action in self.held.difference(&held) {
+				rec.push(tick, *action, StepKind::Up);
+			}
+			if stick != self.stick {
+				rec.push_stick(tick, stick.0, stick.1);
+			}
+		}
+		self.held = held;
+		self.stick = stick;
+		self.tick += 1;
+	}
+
+	/// Queues whatever the key is bound to; false if it isn't bound.
+	pub fn key_press(&mut self, key: Key) -> bool {
+		let actions = self.bindings.actions(key);
+		if self.script.is_none() {
+			for action in actions.iter() {
+				self.press(*action);
+			}
+		}
+		!actions.is_empty()
+	}
+
+	/// Queues whatever a gamepad button is bound to; false if it isn't bound.
+	pub fn button_press(&mut self, button: u32) -> bool {
+		let actions = self.bindings.button_actions(button);
+		if self.script.is_none() {
+			for action in actions.iter() {
+				self.press(*action);
+			}
+		}
+		!actions.is_empty()
+	}
+
+	/// Presses an action once, as if its key had been pressed.
+	pub fn press(&mut self, action: Action) {
+		self.pressed.push(action);
+		if let Some((ref mut rec, start)) = self.recording {
+			let tick = self.tick - start;
+			rec.push(tick, action, StepKind::Tap);
+		}
+	}
+
+	pub fn is_held(&self, action: Action) -> bool {
+		self.held.contains(&action)
+	}
+
+	/// The left stick as it is, -1 to 1 each way (up and left negative).
+	pub fn stick(&self) -> (f32, f32) {
+		self.stick
+	}
+
+	/// Which way (in radians) and how fast (0 to 1) to move, if at all. The
+	/// stick wins over the keys once it's past the dead zone; the keys only
+	/// go eight ways, always at full speed.
+	pub fn movement(&self) -> Option<(f32, f32)> {
+		let (sx, sy) = self.stick;
+		let push = (sx*sx + sy*sy).sqrt();
+		if push > STICK_DEAD_ZONE {
+			let speed = ((push - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)).min(1.0);
+			return Some((sy.atan2(sx), speed));
+		}
+		let dx = self.is_held(Action::MoveRight) as i32 - self.is_held(Action::MoveLeft) as i32;
+		let dy = self.is_held(Action::MoveDown) as i32 - self.is_held(Action::MoveUp) as i32;
+		if dx == 0 && dy == 0 {
+			None
+		} else {
+			Some(((dy as f32).atan2(dx as f32), 1.0))
+		}
+	}
+
+	/// Presses since the last call, oldest first.
+	pub fn take_pressed(&mut self) -> Vec<Action> {
+		self.pressed.drain(..).collect()
+	}
+
+	/// Forgets held actions and pending presses, e.g. after a menu had
+	/// the keyboard.
+	pub fn clear(&mut self) {
+		self.held.clear();
+		self.pressed.clear();
+		self.stick = (0.0, 0.0);
+	}
+}
+
+// first gamepad plugged in, if any
+fn first_pad() -> Option<u32> {
+	(0..MAX_PADS).find(|&pad| joystick::is_connected(pad))
+}
@@ -5,15 +5,25 @@ extern crate nalgebra as na;
 extern crate rustc_serialize;
 extern crate poglgame;
 extern crate recs;
+extern crate image;
+extern crate num_cpus;
+extern crate clock_ticks;
+extern crate docopt;
 
-use std::env::args;
-use std::collections::{HashSet, HashMap};
-use std::iter::FromIterator;
-use std::cell::Cell;
+use std::process;
+use std::io::{stderr, Write};
 
-use old_engine::launch;
-use gameplay::GameplayScreen;
-use generator::generate_default;
+use old_engine::launch_at_rate;
+use gameplay::{Resources, RunSetup};
+use menus::TitleScreen;
+use generator::{DungeonParams, Features, generate_with};
+use world::{TILES_FILE, load_tile_map};
+use seed::Seed;
+use gfx::{SpriteRegistry, TILE_SPRITES};
+use timestep::DEFAULT_TICK_RATE;
+use cli::Args;
+use config::{Config, CONFIG_FILE};
+use input::Bindings;
 
 mod util;
 mod generator;
@@ -23,8 +33,23 @@ mod solver;
 mod old_engine;
 mod collision;
 mod animation;
+mod audio;
 mod entities;
 mod gameplay;
+mod validate;
+mod gen_error;
+mod atlas;
+mod tiled;
+mod render;
+mod stats;
+mod seed;
+mod hud;
+mod input;
+mod menus;
+mod timestep;
+mod cli;
+mod config;
+mod export;
 
 mod utils;
 mod components;
@@ -39,41 +64,131 @@ mod test_search;
 mod test_json;
 mod test_new;
 mod test_dungeon;
+mod test_validate;
+mod test_tiled;
+mod test_stats;
+mod test_golden;
+mod test_sound;
+mod test_input;
+mod test_config;
+mod test_anim;
+mod test_timestep;
+mod test_autotile;
 
 use utils::float;
 use screens::GameplayScreen as NewGameplayScreen;
+use screens::FixedStepScreen;
 
 fn main() {
 
-    let use_new = Cell::new(false);
-    let run_game = Cell::new(true);
-    let mut fn_map: HashMap<String, Box<Fn()>> = HashMap::new();
-    fn_map.insert("--gen".into(),
-            Box::new( || { test_gen::main(); run_game.set(false); } ));
-    fn_map.insert("--search".into(),
-            Box::new( || { test_search::main(); run_game.set(false); } ));
-    fn_map.insert("--json".into(),
-            Box::new( || { test_json::main(); run_game.set(false); } ));
-    fn_map.insert("--dungeon".into(),
-            Box::new( || { test_dungeon::main(); run_game.set(false); } ));
-    fn_map.insert("--new".into(),
-            Box::new( || { use_new.set(true); } ));
-
-    let unique_args: HashSet<String> = HashSet::from_iter(args());
-
-    for arg in unique_args.iter() {
-        fn_map.remove(arg).map(|ref mut f| f());
+    let args = cli::parse();
+    if let Err(e) = run(&args) {
+        writeln!(stderr(), "Error: {}", e).ok();
+        process::exit(1);
     }
+}
 
-    if !run_game.get() { return; }
+fn run(args: &Args) -> Result<(), String> {
 
-    if use_new.get() {
-        let w = 800;
-        let h = 800;
-        let scr = NewGameplayScreen::new(w as float, h as float);
+    let params = match args.flag_params {
+        Some(ref path) => try!(DungeonParams::load(path)),
+        None => DungeonParams::default(),
+    };
+    try!(params.check().map_err(|e| format!("{}", e)));
+
+    if args.cmd_gen {
+        let features = try!(Features::load_default());
+        try!(export::main(try!(cli::seeds(args, 1)), args.flag_format, &params, &features));
+    } else if args.cmd_validate {
+        let features = try!(Features::load_default());
+        try!(test_validate::main(try!(cli::seeds(args, test_validate::DEFAULT_COUNT)), &params,
+                &features));
+    } else if args.cmd_stats {
+        let features = try!(Features::load_default());
+        test_stats::main(try!(cli::seed_range(args, test_stats::DEFAULT_COUNT)), &params,
+                &features, args.flag_json_report);
+    } else if args.cmd_golden {
+        let features = try!(Features::load_default());
+        if args.flag_update { test_golden::update(&features); } else { test_golden::main(&features); }
+    } else if args.cmd_search_bench {
+        test_search::main();
+    } else if args.cmd_test {
+        let name = args.arg_name.clone().unwrap_or(String::new());
+        match &name[..] {
+            "gen" => test_gen::main(),
+            "dungeon" => test_dungeon::main(),
+            "json" => test_json::main(),
+            "tiled" => test_tiled::main(),
+            "sound" => test_sound::main(),
+            "input" => test_input::main(),
+            "config" => test_config::main(),
+            "anim" => test_anim::main(),
+            "timestep" => test_timestep::main(),
+            "autotile" => test_autotile::main(),
+            _ => return Err(format!("no test called `{}` (see --help)", name)),
+        }
+    } else {
+        try!(play(args, &params));
+    }
+    Ok(())
+}
+
+fn play(args: &Args, params: &DungeonParams) -> Result<(), String> {
+
+    let tick_rate = args.flag_tick_rate.unwrap_or(DEFAULT_TICK_RATE);
+    if !(tick_rate > 0.0) {
+        return Err(format!("--tick-rate must be more than 0, got {}", tick_rate));
+    }
+    let config = Config::load(args.flag_config.as_ref().map_or(CONFIG_FILE, |p| &p[..]));
+    let window = match args.flag_window {
+        Some(ref size) => Some(try!(cli::parse_window_size(size))),
+        None => None,
+    };
+
+    if args.flag_new {
+        let (w, h) = window.unwrap_or(config.new_window);
+        let scr = FixedStepScreen::new(
+                NewGameplayScreen::new(w as float, h as float), tick_rate);
         poglgame::launch(scr, "Rusty Rogue", w, h);
+        return Ok(());
+    }
+    let (w, h) = window.unwrap_or(config.window);
+
+    // catch missing or broken sprite files before opening a window
+    let mut sprites = try!(SpriteRegistry::load_default()
+            .map_err(|e| format!("Failed to load sprites: {}", e)));
+    try!(sprites.check(&TILE_SPRITES)
+            .map_err(|e| format!("Failed to load sprites: {}", e)));
+    try!(sprites.load_anims(&gameplay::anim_sets())
+            .map_err(|e| format!("Failed to load animations: {}", e)));
+    let tiles = try!(load_tile_map(TILES_FILE)
+            .map_err(|e| format!("Failed to load tile info: {}", e)));
+    let res = Resources::new(sprites, tiles, Bindings::load_default(), params, &config);
+
+    if let Some(ref path) = args.flag_map {
+        // a fixed level; new runs from its menus are generated as usual
+        let dungeon = try!(tiled::load(path, &res.sprites)
+                .map_err(|e| format!("Failed to load {}: {}", path, e)));
+        let setup = RunSetup { dungeon: dungeon, seed: None, res: res };
+        launch_at_rate(setup.start(), "Rusty Rogue", w, h, tick_rate);
+    } else if args.flag_seed.is_some() || hud::load_font().is_none() {
+        // without a font the title menu would be a blank window, so go
+        // straight into a new run instead
+        let seed = match args.flag_seed {
+            Some(ref code) => Seed::new(code),
+            None => {
+                println!("No font to draw the title menu with, starting a new run.");
+                Seed::random()
+            }
+        };
+        println!("Seed: {}", seed);
+        let dungeon = try!(generate_with(&seed, params, &res.features)
+                .map_err(|e| format!("Failed to generate dungeon from seed {}: {}", seed, e)));
+        let setup = RunSetup { dungeon: dungeon, seed: Some(seed), res: res };
+        launch_at_rate(setup.start(), "Rusty Rogue", w, h, tick_rate);
     } else {
-        launch(GameplayScreen::new( &generate_default( 123 ) ), "Rusty Rogue",
-                800, 600);
+        launch_at_rate(TitleScreen::new(&res, false), "Rusty Rogue", w, h,
+                tick_rate);
     }
-}
\ No newline at end of file
+    Ok(())
+}
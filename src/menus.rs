@@ -0,0 +1,560 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use sfml::graphics::{RenderTarget,RenderWindow,Font,Color,RectangleShape};
+use sfml::graphics::rc::Text;
+use sfml::system::Vector2f;
+use sfml::window::keyboard::Key;
+use sfml::window::event::{self,Event};
+
+use old_engine::{Game,Screen,Transition};
+use gameplay::{GameplayScreen,Resources,RunSetup,RunStats};
+use generator::generate_with;
+use hud::load_font;
+use seed::Seed;
+use input::{Input,Action,Bindings};
+
+const TITLE_SIZE: u32 = 28;
+const ITEM_SIZE: u32 = 20;
+const LINE_SIZE: u32 = 16;
+/// longest seed code the title screen will take
+const SEED_MAX_LEN: usize = 32;
+/// how far the stick has to be pushed up or down to move the selection
+const STICK_PUSH: f32 = 0.5;
+
+/// A list of choices, moved through with whatever's bound to moving up and
+/// down (or a gamepad's stick) and picked with confirm or attack. Without a
+/// font it's printed to the console instead of drawn.
+pub struct Menu {
+	title: String,
+	lines: Vec<String>,
+	// label, enabled
+	items: Vec<(String,bool)>,
+	selected: usize,
+	font: Option<Rc<RefCell<Font>>>,
+	// keys come in through key_press and buttons through button_press,
+	// and are acted on in update
+	input: Input,
+	// which way the stick was pushed last tick, so holding it moves once
+	stick_dir: isize,
+}
+
+impl Menu {
+
+	pub fn new(title: &str, bindings: &Bindings) -> Menu {
+		Menu {
+			title: title.to_string(),
+			lines: Vec::new(),
+			items: Vec::new(),
+			selected: 0,
+			font: load_font(),
+			input: Input::new(bindings.clone()),
+			stick_dir: 0,
+		}
+	}
+
+	/// Text shown between the title and the choices.
+	pub fn line(mut self, text: &str) -> Menu {
+		self.lines.push(text.to_string());
+		self
+	}
+
+	pub fn item(mut self, label: &str, enabled: bool) -> Menu {
+		self.items.push((label.to_string(),enabled));
+		// start on the first choice that can be picked
+		if !self.items[self.selected].1 {
+			self.selected = self.items.len()-1;
+		}
+		self
+	}
+
+	pub fn set_lines(&mut self, lines: Vec<String>) {
+		self.lines = lines;
+	}
+
+	/// Queues a key press for the next `update`; false if the key isn't
+	/// bound.
+	pub fn key_press(&mut self, key: Key) -> bool {
+		self.input.key_press(key)
+	}
+
+	/// Queues a gamepad button press for the next `update`; false if the
+	/// button isn't bound.
+	pub fn button_press(&mut self, button: u32) -> bool {
+		self.input.button_press(button)
+	}
+
+	/// Reads the keys and gamepad, once a tick. Moving up or down (or
+	/// pushing the stick) moves the selection, and confirm or attack picks
+	/// it. Returns the item picked, if any, and the other actions pressed
+	/// for the screen to act on (e.g. pause to go back).
+	pub fn update(&mut self) -> (Option<usize>, Vec<Action>) {
+		self.input.update();
+		let (_, sy) = self.input.stick();
+		let dir = if sy <= -STICK_PUSH { -1 } else if sy >= STICK_PUSH { 1 } else { 0 };
+		if dir != 0 && dir != self.stick_dir {
+			self.step(dir);
+		}
+		self.stick_dir = dir;
+		let mut picked = None;
+		let mut others = Vec::new();
+		for action in self.input.take_pressed() {
+			match action {
+				Action::MoveUp => self.step(-1),
+				Action::MoveDown => self.step(1),
+				Action::Confirm | Action::Attack => picked = picked.or(self.pick()),
+				_ => others.push(action),
+			}
+		}
+		(picked, others)
+	}
+
+	/// Prints the menu if it can't be drawn.
+	pub fn show(&self) {
+		if self.font.is_some() { return; }
+		println!("== {} ==",self.title);
+		for line in self.lines.iter() {
+			println!("  {}",line);
+		}
+		for (i, &(ref label, enabled)) in self.items.iter().enumerate() {
+			let mark = if i == self.selected { ">" } else { " " };
+			if enabled {
+				println!("{} {}",mark,label);
+			} else {
+				println!("{} ({})",mark,label);
+			}
+		}
+	}
+
+	/// Draws centered in the window, dimming whatever's below if `overlay`.
+	pub fn draw(&self, window: &mut RenderWindow, overlay: bool) {
+		let view = window.get_default_view();
+		window.set_view(&view);
+		let size = view.get_size();
+
+		if overlay {
+			let mut dim = RectangleShape::new_init(&size).expect("Failed to create menu backdrop");
+			dim.set_fill_color(&Color{ red: 0, green: 0, blue: 0, alpha: 160 });
+			window.draw(&dim);
+		}
+
+		let font = match self.font {
+			Some(ref font) => font.clone(),
+			None => return
+		};
+		let mut y = size.y * 0.25;
+		let mut put = |window: &mut RenderWindow, text: &str, size_px: u32, color: Color| {
+			if let Some(mut t) = Text::new_init(text,font.clone(),size_px) {
+				let w = t.get_local_bounds().width;
+				t.set_color(&color);
+				t.set_position(&Vector2f::new(((size.x - w) / 2.0).floor(), y.floor()));
+				window.draw(&t);
+			}
+			y += size_px as f32 * 1.6;
+		};
+
+		put(window,&self.title,TITLE_SIZE,Color::white());
+		for line in self.lines.iter() {
+			put(window,line,LINE_SIZE,Color{ red: 200, green: 200, blue: 200, alpha: 255 });
+		}
+		for (i, &(ref label, enabled)) in self.items.iter().enumerate() {
+			let color = if !enabled {
+				Color{ red: 100, green: 100, blue: 100, alpha: 255 }
+			} else if i == self.selected {
+				Color::yellow()
+			} else {
+				Color::white()
+			};
+			put(window,label,ITEM_SIZE,color);
+		}
+	}
+
+	// the selected item, if it can be picked
+	fn pick(&self) -> Option<usize> {
+		match self.items.get(self.selected) {
+			Some(&(_, true)) => Some(self.selected),
+			_ => None
+		}
+	}
+
+	// next enabled item up or down, wrapping around
+	fn step(&mut self, dir: isize) {
+		let n = self.items.len() as isize;
+		if n == 0 { return; }
+		let mut i = self.selected as isize;
+		for _ in 0..n {
+			i = (i + dir + n) % n;
+			if self.items[i as usize].1 {
+				self.selected = i as usize;
+				break;
+			}
+		}
+		self.show();
+	}
+
+}
+
+// generates a dungeon and throws away everything else to play it
+fn start_run(res: &Resources, seed: Seed) -> Option<Transition> {
+	match generate_with(&seed,&res.params,&res.features) {
+		Ok(dungeon) => {
+			println!("Seed: {}",seed);
+			let setup = RunSetup { dungeon: dungeon, seed: Some(seed), res: res.clone() };
+			Some(Transition::Reset(Box::new(setup.start())))
+		}
+		Err(e) => {
+			println!("Failed to generate dungeon from seed {}: {}",seed,e);
+			None
+		}
+	}
+}
+
+///////////////////
+// Title screen  //
+///////////////////
+
+pub struct TitleScreen {
+	menu: Menu,
+	res: Resources,
+	// a run is waiting underneath
+	can_continue: bool,
+	// typed so far, while entering a seed
+	seed_entry: Option<String>,
+	next: Option<Transition>,
+}
+
+const TITLE_NEW: usize = 0;
+const TITLE_CONTINUE: usize = 1;
+const TITLE_SEED: usize = 2;
+const TITLE_QUIT: usize = 3;
+
+impl TitleScreen {
+
+	/// `can_continue` when there's a run on the stack below to go back to.
+	pub fn new(res: &Resources, can_continue: bool) -> TitleScreen {
+		TitleScreen {
+			menu: Menu::new("Rusty Rogue",&res.bindings)
+				.item("New game",true)
+				.item("Continue",can_continue)
+				.item("Enter seed",true)
+				.item("Quit",true),
+			res: res.clone(),
+			can_continue: can_continue,
+			seed_entry: None,
+			next: None,
+		}
+	}
+
+	fn set_seed_entry(&mut self, entry: Option<String>) {
+		let lines = match entry {
+			Some(ref code) => vec![format!("Seed: {}_",code), "Return to start, Escape to cancel".to_string()],
+			None => Vec::new()
+		};
+		self.menu.set_lines(lines);
+		self.seed_entry = entry;
+		self.menu.show();
+	}
+
+	fn choose(&mut self, item: usize) {
+		match item {
+			TITLE_NEW => self.next = start_run(&self.res, Seed::random()),
+			TITLE_CONTINUE => self.next = Some(Transition::Pop),
+			TITLE_SEED => self.set_seed_entry(Some(String::new())),
+			TITLE_QUIT => self.next = Some(Transition::Quit),
+			_ => {}
+		}
+	}
+
+}
+
+impl Screen for TitleScreen {
+
+	fn init(&mut self, game: &mut Game, window: &mut RenderWindow) {
+		window.set_title("Rusty Rogue");
+		self.menu.show();
+	}
+
+	fn key_press(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool {
+		// typing a seed, keys are letters rather than actions
+		if let Some(code) = self.seed_entry.clone() {
+			match key {
+				Key::Return if !code.is_empty() => {
+					self.next = start_run(&self.res, Seed::new(&code));
+				}
+				Key::Escape => self.set_seed_entry(None),
+				Key::BackSpace => {
+					let mut code = code;
+					code.pop();
+					self.set_seed_entry(Some(code));
+				}
+				_ => {}
+			}
+			return true;
+		}
+		self.menu.key_press(key)
+	}
+
+	fn event(&mut self, game: &mut Game, window: &mut RenderWindow, e: Event) -> bool {
+		match (e, self.seed_entry.clone()) {
+			(event::TextEntered{code}, Some(mut entry)) => {
+				if (code.is_alphanumeric() || code == '-') && entry.len() < SEED_MAX_LEN {
+					entry.extend(code.to_uppercase());
+					self.set_seed_entry(Some(entry));
+				}
+				true
+			}
+			(event::JoystickButtonPressed{button, ..}, _) => self.menu.button_press(button),
+			_ => false
+		}
+	}
+
+	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Transition {
+		let (picked, actions) = self.menu.update();
+		let back = actions.contains(&Action::Pause);
+		if self.seed_entry.is_some() {
+			// there's no typing seeds on a gamepad, only backing out
+			if back { self.set_seed_entry(None); }
+		} else if let Some(item) = picked {
+			self.choose(item);
+		} else if back && self.can_continue {
+			self.next = Some(Transition::Pop);
+		}
+		self.next.take().unwrap_or(Transition::Stay)
+	}
+
+	fn draw(&self, game: &mut Game, window: &mut RenderWindow) {
+		window.clear(&Color::black());
+		self.menu.draw(window,false);
+	}
+
+}
+
+//////////////////
+// Pause screen //
+//////////////////
+
+pub struct PauseScreen {
+	menu: Menu,
+	stats: RunStats,
+	res: Resources,
+	next: Option<Transition>,
+}
+
+const PAUSE_RESUME: usize = 0;
+const PAUSE_INVENTORY: usize = 1;
+const PAUSE_TITLE: usize = 2;
+const PAUSE_QUIT: usize = 3;
+
+impl PauseScreen {
+
+	pub fn new(stats: &RunStats, setup: &RunSetup) -> PauseScreen {
+		PauseScreen {
+			menu: Menu::new("Paused",&setup.res.bindings)
+				.line(&stats.seed_label)
+				.item("Resume",true)
+				.item("Inventory",true)
+				.item("Main menu",true)
+				.item("Quit",true),
+			stats: stats.clone(),
+			res: setup.res.clone(),
+			next: None,
+		}
+	}
+
+	fn choose(&mut self, item: usize) {
+		match item {
+			PAUSE_RESUME => self.next = Some(Transition::Pop),
+			PAUSE_INVENTORY =>
+				self.next = Some(Transition::Push(Box::new(InventoryScreen::new(&self.stats,&self.res.bindings)))),
+			// the run stays underneath, so the title screen can go back to it
+			PAUSE_TITLE =>
+				self.next = Some(Transition::Replace(Box::new(TitleScreen::new(&self.res,true)))),
+			PAUSE_QUIT => self.next = Some(Transition::Quit),
+			_ => {}
+		}
+	}
+
+}
+
+impl Screen for PauseScreen {
+
+	fn init(&mut self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.show();
+	}
+
+	fn resume(&mut self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.show();
+	}
+
+	fn key_press(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool {
+		self.menu.key_press(key)
+	}
+
+	fn event(&mut self, game: &mut Game, window: &mut RenderWindow, e: Event) -> bool {
+		match e {
+			event::JoystickButtonPressed{button, ..} => self.menu.button_press(button),
+			_ => false
+		}
+	}
+
+	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Transition {
+		let (picked, actions) = self.menu.update();
+		if let Some(item) = picked {
+			self.choose(item);
+		} else if actions.contains(&Action::Pause) {
+			self.next = Some(Transition::Pop);
+		}
+		self.next.take().unwrap_or(Transition::Stay)
+	}
+
+	fn draw(&self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.draw(window,true);
+	}
+
+	fn is_overlay(&self) -> bool { true }
+
+}
+
+//////////////////////
+// Inventory screen //
+//////////////////////
+
+pub struct InventoryScreen {
+	menu: Menu,
+	next: Option<Transition>,
+}
+
+impl InventoryScreen {
+
+	pub fn new(stats: &RunStats, bindings: &Bindings) -> InventoryScreen {
+		let mut menu = Menu::new("Inventory",bindings);
+		if stats.treasure == 0 && stats.keys == 0 {
+			menu = menu.line("Nothing yet.");
+		}
+		if stats.treasure > 0 {
+			menu = menu.line(&format!("Treasure x{}",stats.treasure));
+		}
+		if stats.keys > 0 {
+			menu = menu.line(&format!("Keys x{}",stats.keys));
+		}
+		InventoryScreen {
+			menu: menu.item("Back",true),
+			next: None,
+		}
+	}
+
+}
+
+impl Screen for InventoryScreen {
+
+	fn init(&mut self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.show();
+	}
+
+	fn key_press(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool {
+		self.menu.key_press(key)
+	}
+
+	fn event(&mut self, game: &mut Game, window: &mut RenderWindow, e: Event) -> bool {
+		match e {
+			event::JoystickButtonPressed{button, ..} => self.menu.button_press(button),
+			_ => false
+		}
+	}
+
+	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Transition {
+		let (picked, actions) = self.menu.update();
+		if picked.is_some() || actions.contains(&Action::Pause) || actions.contains(&Action::Inventory) {
+			self.next = Some(Transition::Pop);
+		}
+		self.next.take().unwrap_or(Transition::Stay)
+	}
+
+	fn draw(&self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.draw(window,true);
+	}
+
+	fn is_overlay(&self) -> bool { true }
+
+}
+
+//////////////////////
+// Game over screen //
+//////////////////////
+
+pub struct GameOverScreen {
+	menu: Menu,
+	setup: RunSetup,
+	next: Option<Transition>,
+}
+
+const OVER_RETRY: usize = 0;
+const OVER_NEW: usize = 1;
+const OVER_TITLE: usize = 2;
+const OVER_QUIT: usize = 3;
+
+impl GameOverScreen {
+
+	/// `setup` is the run that just ended, for trying it again.
+	pub fn new(stats: &RunStats, setup: &RunSetup) -> GameOverScreen {
+		let minutes = (stats.time / 60.0) as u32;
+		let seconds = (stats.time % 60.0) as u32;
+		GameOverScreen {
+			menu: Menu::new("You died",&setup.res.bindings)
+				.line(&stats.seed_label)
+				.line(&format!("Survived {}:{:02}",minutes,seconds))
+				.line(&format!("Monsters slain: {}",stats.kills))
+				.line(&format!("Treasure found: {}",stats.treasure))
+				.item("Try again",true)
+				.item("New game",true)
+				.item("Main menu",true)
+				.item("Quit",true),
+			setup: setup.clone(),
+			next: None,
+		}
+	}
+
+	fn choose(&mut self, item: usize) {
+		match item {
+			OVER_RETRY => self.next = Some(Transition::Reset(Box::new(self.setup.start()))),
+			OVER_NEW => self.next = start_run(&self.setup.res, Seed::random()),
+			OVER_TITLE =>
+				self.next = Some(Transition::Reset(Box::new(TitleScreen::new(&self.setup.res,false)))),
+			OVER_QUIT => self.next = Some(Transition::Quit),
+			_ => {}
+		}
+	}
+
+}
+
+impl Screen for GameOverScreen {
+
+	fn init(&mut self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.show();
+	}
+
+	fn key_press(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool {
+		self.menu.key_press(key)
+	}
+
+	fn event(&mut self, game: &mut Game, window: &mut RenderWindow, e: Event) -> bool {
+		match e {
+			event::JoystickButtonPressed{button, ..} => self.menu.button_press(button),
+			_ => false
+		}
+	}
+
+	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Transition {
+		if let Some(item) = self.menu.update().0 {
+			self.choose(item);
+		}
+		self.next.take().unwrap_or(Transition::Stay)
+	}
+
+	fn draw(&self, game: &mut Game, window: &mut RenderWindow) {
+		self.menu.draw(window,true);
+	}
+
+	fn is_overlay(&self) -> bool { true }
+
+}
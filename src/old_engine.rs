@@ -5,14 +5,15 @@ use sfml::window::event::Event;
 use sfml::system::{Clock, Time, sleep, Vector2f};
 use sfml::graphics::{Color,VertexArray,Lines};
 
-use sfml::audio::SoundBuffer;
-use sfml::audio::rc::Sound;
-use sfml::audio::Music;
-use sfml::audio;
-
-use util::get_rc_resource;
+use audio::SoundManager;
+use timestep::{FixedStep,DEFAULT_TICK_RATE};
 
 pub fn launch<T: Screen>(screen: T, title: &str, w: u32, h: u32) {
+	launch_at_rate(screen, title, w, h, DEFAULT_TICK_RATE)
+}
+
+/// Runs `update` `tick_rate` times a second, however fast frames are drawn.
+pub fn launch_at_rate<T: Screen>(screen: T, title: &str, w: u32, h: u32, tick_rate: f64) {
 
 	// init window
 	let setting: ContextSettings = ContextSettings::default();
@@ -24,25 +25,21 @@ pub fn launch<T: Screen>(screen: T, title: &str, w: u32, h: u32) {
 	// create Game struct
 	let mut game = Game::new();
 
-	// grab screen
-	let mut s: Box<Screen> = Box::new(screen);
-	s.init(&mut game, &mut window);
-
-	// init sound idx remover
-	let mut removed: Vec<usize> = Vec::new();
+	// screen stack; only the top one gets input and updates
+	let mut stack: Vec<Box<Screen>> = vec![Box::new(screen)];
+	stack[0].init(&mut game, &mut window);
 
 	// init timer
 	let mut t = Timer::new();
+	let mut step = FixedStep::new(tick_rate);
+	let tick = step.tick() as f32;
 
 	// game loop
 	while window.is_open() {
 
-		// TODO implement special functions
-		// for handling various input events, i.e. key
-		// presses, key releases, etc.
-
 		// process events
 		loop {
+			let s = stack.last_mut().expect("Screen stack is empty");
 			match window.poll_event() {
 				event::NoEvent => break,
 				event::KeyPressed{code, ..} => {
@@ -59,31 +56,65 @@ pub fn launch<T: Screen>(screen: T, title: &str, w: u32, h: u32) {
 			}
 		}
 
-		// update game state
-		let ret = s.update(&mut game, &mut window, t.get_delta());
+		// update game state in fixed ticks; a new screen waits for the next frame
+		let ticks = step.advance(t.get_delta() as f64);
+		for _ in 0..ticks {
+			let ret = stack.last_mut().expect("Screen stack is empty")
+				.update(&mut game, &mut window, tick);
+			if apply_transition(ret, &mut stack, &mut game, &mut window) {
+				break;
+			}
+		}
+		if stack.is_empty() { break; }
 
-		// switch to new state if necessary
-		match ret {
-			Some(ns) => { s = ns; s.init(&mut game, &mut window); },
-			None => { }
+		// draw from the topmost screen that isn't an overlay, upwards
+		let alpha = step.alpha() as f32;
+		let base = stack.iter().rposition(|s| !s.is_overlay()).unwrap_or(0);
+		for s in stack[base..].iter_mut() {
+			s.interpolate(alpha);
+		}
+		for s in stack[base..].iter() {
+			s.draw(&mut game, &mut window);
 		}
 		window.display();
 
-		// clean up sounds playing
-		for i in 0..game.sounds.len() {
-			match game.sounds[i].get_status() {
-				audio::Stopped => {
-					removed.push(i);
-				}
-				_ => {}
+		// clean up sounds that have finished
+		game.audio.update();
+	}
+}
+
+// push, pop or swap screens; true if the stack changed
+fn apply_transition(ret: Transition, stack: &mut Vec<Box<Screen>>, game: &mut Game,
+		window: &mut RenderWindow) -> bool {
+	match ret {
+		Transition::Stay => return false,
+		Transition::Push(mut ns) => {
+			ns.init(game, window);
+			stack.push(ns);
+		}
+		Transition::Pop => {
+			stack.pop();
+			match stack.last_mut() {
+				Some(s) => s.resume(game, window),
+				None => window.close()
 			}
 		}
-		
-		for r in removed.iter().rev() {
-			game.sounds.swap_remove(*r);
+		Transition::Replace(mut ns) => {
+			stack.pop();
+			ns.init(game, window);
+			stack.push(ns);
+		}
+		Transition::Reset(mut ns) => {
+			stack.clear();
+			ns.init(game, window);
+			stack.push(ns);
+		}
+		Transition::Quit => {
+			stack.clear();
+			window.close();
 		}
-		removed.clear();
 	}
+	true
 }
 
 fn event_default(window: &mut RenderWindow, event: Event) -> bool {
@@ -99,8 +130,7 @@ fn event_default(window: &mut RenderWindow, event: Event) -> bool {
 ////////////////////////////
 
 pub struct Game {
-	sounds: Vec<Sound>,
-	music: Option<Music>,
+	pub audio: SoundManager,
 	va: VertexArray,
 }
 
@@ -108,8 +138,7 @@ impl Game {
 
 	pub fn new() -> Game {
 		Game {
-			sounds: Vec::new(),
-			music: None,
+			audio: SoundManager::load_default(),
 			va: VertexArray::new().expect("Couldn't create VertexArray for Game"),
 		}
 	}
@@ -144,23 +173,17 @@ impl Game {
 		window.draw(&self.va);
 	}
 
-	pub fn play_sound_buffer(&mut self, buffer: &SoundBuffer) {
-		let buf = buffer.clone().expect("Error cloning buffer reference");
-		let rc_buf = get_rc_resource( buf );
-		let mut sound = Sound::new_with_buffer( rc_buf ).expect("Error creating sound from buffer");
-		sound.play();
-		self.sounds.push(sound);
+	/// Plays a sound from res/snd/sounds.json heard from (x,y), in tiles.
+	pub fn play_sound_at(&mut self, name: &str, x: f32, y: f32) {
+		self.audio.play_at(name, x, y);
 	}
 
-	pub fn loop_music_file(&mut self, song_full_path: &str) {
-		let mut song = Music::new_from_file(song_full_path).expect("Error loading music file");
-		song.set_loop(true);
-		song.play();
-		self.music = Some(song);
+	pub fn loop_floor_music(&mut self, floor: u32) {
+		self.audio.play_floor_music(floor);
 	}
 
 	pub fn has_music(&self) -> bool {
-		return self.music.is_some();
+		self.audio.has_music()
 	}
 
 }
@@ -169,9 +192,35 @@ impl Game {
 // Game screen interface //
 ///////////////////////////
 
+/// What to do with the screen stack after an update.
+pub enum Transition {
+	Stay,
+	/// put a screen on top of this one
+	Push(Box<Screen>),
+	/// close this screen, uncovering the one below
+	Pop,
+	/// swap this screen for another
+	Replace(Box<Screen>),
+	/// throw away every screen and start over with this one
+	Reset(Box<Screen>),
+	/// close the window
+	Quit,
+}
+
 pub trait Screen {
 	fn init(&mut self, game: &mut Game, window: &mut RenderWindow) { /* empty by default */ }
-	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Option<Box<Screen>>;
+	/// Called when the screen on top of this one pops off.
+	fn resume(&mut self, game: &mut Game, window: &mut RenderWindow) { /* empty by default */ }
+	/// Only the top screen is updated, a fixed `delta` at a time.
+	fn update(&mut self, game: &mut Game, window: &mut RenderWindow, delta: f32) -> Transition;
+	/// Called before drawing with how far (0 to 1) the clock is into the
+	/// next update, to draw moving things in between.
+	fn interpolate(&mut self, alpha: f32) { /* empty by default */ }
+	/// Called every frame for the top screen, and for the ones under it as
+	/// long as everything above them is an overlay.
+	fn draw(&self, game: &mut Game, window: &mut RenderWindow);
+	/// Overlays are drawn on top of the screen below instead of hiding it.
+	fn is_overlay(&self) -> bool { false }
 	fn event(&mut self, game: &mut Game, window: &mut RenderWindow, event: Event) -> bool { false }
 	fn key_press(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool { false }
 	fn key_release(&mut self, game: &mut Game, window: &mut RenderWindow, key: Key) -> bool { false }
@@ -197,7 +246,9 @@ impl Timer {
 		Timer {
 			clock: Clock::new(),
 			min_delta: Time::with_seconds(0.),
-			max_delta: Time::with_seconds(1./20.)
+			// long stalls are FixedStep's problem; this just keeps a
+			// debugger pause from counting as one huge frame
+			max_delta: Time::with_seconds(1.)
 		}
 	}
 
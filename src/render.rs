@@ -0,0 +1,177 @@
+// software rendering of whole dungeons to images -- no window or GPU needed
+
+use image::{self, ImageBuffer, ImageResult, Rgba, RgbaImage};
+
+use atlas;
+use atlas::SpritePos;
+use generator::{Dungeon, TileType, Floor, Wall, Door, Corridor, StairsUp, StairsDown, Unknown};
+use generator::DoorState::{Open, Secret};
+use generator::{Monster, Treasure, Key, Missingno};
+use gfx::{SpriteRegistry, RegistryResult};
+use gfx::autotile;
+use util::get_gfx_path;
+
+pub type Color = [u8; 4];
+
+pub const BACKGROUND: Color = [0, 0, 0, 255];
+pub const PATH_COLOR: Color = [255, 0, 0, 255];
+pub const TREASURE_COLOR: Color = [255, 215, 0, 255];
+pub const KEY_COLOR: Color = [0, 200, 255, 255];
+
+#[derive(Clone, Default)]
+pub struct RenderOptions {
+    /// draw the hero, monsters and items on top of the tiles
+    pub entities: bool,
+    /// tile coordinates to connect with a line, e.g. a solver path
+    pub path: Option<Vec<(isize, isize)>>,
+}
+
+pub fn load_atlas() -> ImageResult<RgbaImage> {
+    image::open(&get_gfx_path(atlas::ATLAS_FILE)).map(|img| img.to_rgba())
+}
+
+/// Composites the dungeon the same way `GameplayScreen` draws it.
+pub fn render_dungeon(d: &Dungeon, sheet: &RgbaImage, sprites: &SpriteRegistry,
+                      opts: &RenderOptions) -> RegistryResult<RgbaImage> {
+    let tsz = atlas::TILE_SIZE as u32;
+    let (w, h) = (d.width() as u32 * tsz, d.height() as u32 * tsz);
+    let mut img = ImageBuffer::from_pixel(w, h, Rgba { data: BACKGROUND });
+
+    let floor = try!(sprites.position("floor"));
+    let corridor = try!(sprites.position("corridor"));
+    let door = try!(sprites.position("door"));
+    let stairs_up = try!(sprites.position("stairs_up"));
+    let stairs_down = try!(sprites.position("stairs_down"));
+    let walls = try!(sprites.require("wall"));
+
+    let wall_at = |x: isize, y: isize| d.get_tile_type(x, y).map_or(true, looks_like_wall);
+    for tile in d.get_tile_vector().iter() {
+        let (px, py) = tile_px(tile.x, tile.y);
+        let mask = autotile::neighbor_mask(tile.x, tile.y, &wall_at);
+        if looks_like_wall(tile.t) {
+            for &(x, y) in autotile::wall_variants(walls, mask).iter() {
+                blit(&mut img, sheet, (x as usize, y as usize), px, py);
+            }
+            continue;
+        }
+
+        // floor, then the edges of any walls beside it, then a shut door
+        let (layers, door_layer) = match tile.t {
+            Floor => (vec![floor], None),
+            Door(Open) => (vec![corridor], None),
+            // drawn as walls, above
+            Door(Secret) | Wall | Unknown => (Vec::new(), None),
+            Door(_) => (vec![corridor], Some(door)),
+            Corridor => (vec![corridor], None),
+            StairsUp => (vec![floor, stairs_up], None),
+            StairsDown => (vec![floor, stairs_down], None),
+        };
+        for &pos in layers.iter() {
+            blit(&mut img, sheet, pos, px, py);
+        }
+        let nudge = autotile::EDGE_NUDGE;
+        for &((x, y), (ox, oy)) in autotile::edge_variants(walls, mask).iter() {
+            blit(&mut img, sheet, (x as usize, y as usize), px + ox * nudge, py + oy * nudge);
+        }
+        if let Some(pos) = door_layer {
+            blit(&mut img, sheet, pos, px, py);
+        }
+    }
+
+    if opts.entities {
+        for tile in d.get_tile_vector().iter() {
+            let (px, py) = tile_px(tile.x, tile.y);
+            match tile.e {
+                Some(Monster(num)) => {
+                    let pos = atlas::MONSTERS[num % atlas::MONSTERS.len()];
+                    blit(&mut img, sheet, pos, px, py);
+                }
+                Some(Treasure) => marker(&mut img, px, py, TREASURE_COLOR),
+                Some(Key) => marker(&mut img, px, py, KEY_COLOR),
+                Some(Missingno) | None => {}
+            }
+        }
+        let (sx, sy) = d.start_coords;
+        let (px, py) = tile_px(sx, sy);
+        blit(&mut img, sheet, atlas::HERO, px, py);
+    }
+
+    match opts.path {
+        None => {}
+        Some(ref path) => {
+            let half = tsz as isize / 2;
+            for pair in path.windows(2) {
+                let (ax, ay) = tile_px(pair[0].0, pair[0].1);
+                let (bx, by) = tile_px(pair[1].0, pair[1].1);
+                line(&mut img, (ax + half, ay + half), (bx + half, by + half), PATH_COLOR);
+            }
+        }
+    }
+
+    Ok(img)
+}
+
+/// secret doors pass for walls
+fn looks_like_wall(t: TileType) -> bool {
+    t == Wall || t == Door(Secret) || t == Unknown
+}
+
+fn tile_px(x: isize, y: isize) -> (isize, isize) {
+    let tsz = atlas::TILE_SIZE as isize;
+    (x * tsz, y * tsz)
+}
+
+/// Alpha-blends one atlas sprite onto `img` with its top left at (px, py).
+fn blit(img: &mut RgbaImage, sheet: &RgbaImage, pos: SpritePos, px: isize, py: isize) {
+    let tsz = atlas::TILE_SIZE as u32;
+    let (sx, sy) = (pos.0 as u32 * tsz, pos.1 as u32 * tsz);
+    for y in 0..tsz {
+        for x in 0..tsz {
+            let src = sheet.get_pixel(sx + x, sy + y).data;
+            plot(img, px + x as isize, py + y as isize, src);
+        }
+    }
+}
+
+fn marker(img: &mut RgbaImage, px: isize, py: isize, color: Color) {
+    let tsz = atlas::TILE_SIZE as isize;
+    for y in (tsz / 4)..(tsz * 3 / 4) {
+        for x in (tsz / 4)..(tsz * 3 / 4) {
+            plot(img, px + x, py + y, color);
+        }
+    }
+}
+
+// Bresenham
+fn line(img: &mut RgbaImage, a: (isize, isize), b: (isize, isize), color: Color) {
+    let (mut x, mut y) = a;
+    let (x2, y2) = b;
+    let dx = (x2 - x).abs();
+    let dy = -(y2 - y).abs();
+    let sx = if x < x2 { 1 } else { -1 };
+    let sy = if y < y2 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        plot(img, x, y, color);
+        if x == x2 && y == y2 { break; }
+        let e2 = 2 * err;
+        if e2 >= dy { err += dy; x += sx; }
+        if e2 <= dx { err += dx; y += sy; }
+    }
+}
+
+fn plot(img: &mut RgbaImage, x: isize, y: isize, src: Color) {
+    if x < 0 || y < 0 || x >= img.width() as isize || y >= img.height() as isize {
+        return;
+    }
+    let (x, y) = (x as u32, y as u32);
+    let alpha = src[3] as u32;
+    if alpha == 0 { return; }
+    let dst = img.get_pixel(x, y).data;
+    let mut out = [0u8; 4];
+    for i in 0..3 {
+        out[i] = ((src[i] as u32 * alpha + dst[i] as u32 * (255 - alpha)) / 255) as u8;
+    }
+    out[3] = 255;
+    img.put_pixel(x, y, Rgba { data: out });
+}
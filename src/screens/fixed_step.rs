@@ -0,0 +1,55 @@
+use poglgame::event::*;
+use poglgame::GlGraphics;
+use poglgame::Context;
+use poglgame::screen::*;
+use poglgame::game_input::*;
+
+use utils::float;
+use timestep::FixedStep;
+
+/// Screens that can draw partway between two ticks.
+pub trait Interpolate {
+    /// `alpha` (0 to 1) is how far the clock is into the next tick.
+    fn interpolate(&mut self, alpha: float) {}
+}
+
+/// Runs a screen's `update` in fixed ticks, whatever rate poglgame calls it
+/// at, the same way `old_engine::launch` does for the SFML screens.
+///
+/// The wrapped screen sees every poglgame update so no key press is missed:
+/// when no tick is due it still gets one with `dt` of 0, and when several
+/// are due the same input is seen by each of them.
+pub struct FixedStepScreen<S> {
+    inner: S,
+    step: FixedStep,
+}
+
+impl<S: Screen + Interpolate> FixedStepScreen<S> {
+    pub fn new(inner: S, tick_rate: float) -> Self {
+        FixedStepScreen {
+            inner: inner,
+            step: FixedStep::new(tick_rate),
+        }
+    }
+}
+
+impl<S: Screen + Interpolate> Screen for FixedStepScreen<S> {
+    fn update(&mut self, args: &UpdateArgs, im: &GameInput)
+        -> UpdateResult
+    {
+        let ticks = self.step.advance(args.dt);
+        let dt = if ticks == 0 { 0.0 } else { self.step.tick() };
+        for _ in 0..ticks.max(1) {
+            match self.inner.update(&UpdateArgs { dt: dt }, im) {
+                UpdateResult::Done => {}
+                other => return other,
+            }
+        }
+        UpdateResult::Done
+    }
+    fn draw(&mut self, args: &RenderArgs, c: Context, gl: &mut GlGraphics) {
+        let alpha = self.step.alpha();
+        self.inner.interpolate(alpha);
+        self.inner.draw(args, c, gl);
+    }
+}
@@ -16,10 +16,14 @@ use utils::*;
 use rect::*;
 use components::*;
 use physics::*;
+use super::Interpolate;
 
 pub struct GameplayScreen {
     ecs: Ecs,
     hits: Vec<Rectf>,
+    // positions as of the start of the last tick, to draw in between
+    prev: Vec<(EntityId, Vec2f)>,
+    alpha: float,
     w: float,
     h: float,
 }
@@ -43,6 +47,8 @@ impl GameplayScreen {
         GameplayScreen {
             ecs: ecs,
             hits: hits,
+            prev: Vec::new(),
+            alpha: 1.0,
             w: 9999.0,
             h: 9999.0,
         }
@@ -56,6 +62,11 @@ impl Screen for GameplayScreen {
         if im.was_key_pressed(&Key::Escape) { 
             return UpdateResult::Quit;
         }
+        if args.dt == 0.0 {
+            return UpdateResult::Done;
+        }
+        self.prev = self.ecs.collect_with::<Position>().iter()
+                .map(|&(id, pos)| (id, pos.0)).collect();
         self.hits.clear();
         let colliders =
             self.ecs.collect_with_3::<Position, Velocity, Collision>();
@@ -115,6 +126,10 @@ impl Screen for GameplayScreen {
         let color = [0.0, 0.5, 0.5, 1.0];
         for id in self.ecs.iter_ids() {
             let pos = self.ecs.get::<Position>(id).unwrap();
+            let pos = match self.prev.iter().find(|&&(pid, _)| pid == id) {
+                Some(&(_, prev)) => Position(prev + (pos.0 - prev) * self.alpha),
+                None => pos,
+            };
             let col = self.ecs.get::<Collision>(id).unwrap();
             let tf = c.transform.clone();
             // let tf = tf
@@ -132,4 +147,10 @@ impl Screen for GameplayScreen {
             rectangle(highlight, r.rounded(), c.transform, gl);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Interpolate for GameplayScreen {
+    fn interpolate(&mut self, alpha: float) {
+        self.alpha = alpha;
+    }
+}
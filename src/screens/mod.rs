@@ -1,2 +1,4 @@
 mod gameplay2;
-pub use self::gameplay2::GameplayScreen;
\ No newline at end of file
+mod fixed_step;
+pub use self::gameplay2::GameplayScreen;
+pub use self::fixed_step::{FixedStepScreen, Interpolate};
\ No newline at end of file
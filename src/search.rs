@@ -2,7 +2,57 @@ use graph::{Graph,GraphNode};
 use std::collections::{HashMap,HashSet};
 
 pub trait SearchStrategy {
-	fn solve(&self, graph: &Graph, start: (isize,isize), end: (isize,isize)) -> Option<Vec<(isize,isize)>>;
+	fn solve_with(&self, graph: &Graph, start: (isize,isize), end: (isize,isize),
+			profile: &CostProfile) -> Option<Vec<(isize,isize)>>;
+
+	fn solve(&self, graph: &Graph, start: (isize,isize), end: (isize,isize)) -> Option<Vec<(isize,isize)>> {
+		self.solve_with(graph, start, end, &CostProfile::new())
+	}
+}
+
+/// Per-creature terrain costs, e.g. a swimmer that finds water cheap.
+/// Terrain without an override uses the graph's base cost.
+#[derive(Clone,Debug,Default)]
+pub struct CostProfile {
+	overrides: HashMap<String,f32>,
+	// terrain the creature can't enter at all
+	blocked: HashSet<String>,
+}
+
+impl CostProfile {
+	pub fn new() -> CostProfile {
+		CostProfile { overrides: HashMap::new(), blocked: HashSet::new() }
+	}
+
+	pub fn with(mut self, terrain: &str, cost: f32) -> CostProfile {
+		self.overrides.insert(terrain.to_string(), cost);
+		self
+	}
+
+	/// Keeps paths off the terrain entirely, e.g. closed doors for
+	/// something that can't open them.
+	pub fn without(mut self, terrain: &str) -> CostProfile {
+		self.blocked.insert(terrain.to_string());
+		self
+	}
+
+	pub fn passable(&self, graph: &Graph, node: &GraphNode) -> bool {
+		graph.get_terrain(node).map_or(true, |t| !self.blocked.contains(t))
+	}
+
+	/// cost of a tile with the given terrain and base cost
+	pub fn cost(&self, terrain: Option<&str>, base: f32) -> f32 {
+		terrain.and_then(|t| self.overrides.get(t)).cloned().unwrap_or(base)
+	}
+
+	pub fn node_cost(&self, graph: &Graph, node: &GraphNode) -> f32 {
+		self.cost(graph.get_terrain(node), graph.get_base_cost(node))
+	}
+
+	/// lower bound on any step's cost multiplier, to keep the heuristic admissible
+	pub fn min_cost(&self, graph: &Graph) -> f32 {
+		self.overrides.values().fold(graph.get_min_cost(), |min, &c| if c < min { c } else { min })
+	}
 }
 
 pub struct AStarSearch {
@@ -73,7 +123,11 @@ impl AStarSearch {
 
 impl SearchStrategy for AStarSearch {
 
-	fn solve(&self, graph: &Graph, start: (isize,isize), end: (isize,isize)) -> Option<Vec<(isize,isize)>> {
+	fn solve_with(&self, graph: &Graph, start: (isize,isize), end: (isize,isize),
+			profile: &CostProfile) -> Option<Vec<(isize,isize)>> {
+
+		// heuristics assume every step costs at least its length
+		let h_scale = profile.min_cost(graph);
 
 		let start_node = graph.find_node_at_tuple(start).expect("ERROR: Could not find start node :-(");
 		let end_node = graph.find_node_at_tuple(end).expect("ERROR: Could not find end node :-(");
@@ -94,7 +148,7 @@ impl SearchStrategy for AStarSearch {
 
 		// initialize f and g score maps
 		g_score.insert(start_node, 0f32);
-		f_score.insert(start_node, g_score.get(&start_node).expect("Start not in g_score") + self.h(&start_node,&end_node));
+		f_score.insert(start_node, g_score.get(&start_node).expect("Start not in g_score") + h_scale * self.h(&start_node,&end_node));
 
 		//while loop until unvisited is empty
 		//ie while unvisited not empty
@@ -122,18 +176,18 @@ impl SearchStrategy for AStarSearch {
 			closed.insert(current_node);
 			// for all neighbors in open set... do stuff
 			for neighbor_ref in graph.get_neighbors(&current_node).expect("Couldn't find node in graph").iter() {
-				if closed.contains(neighbor_ref) {
+				if closed.contains(neighbor_ref) || !profile.passable(graph,neighbor_ref) {
 					continue
 				}
 				let tentative_g = g_score.get(&current_node).expect("NO g score for cur")
-					+ current_node.distance_to(neighbor_ref);
+					+ current_node.distance_to(neighbor_ref) * profile.node_cost(graph,neighbor_ref);
 				// update f and g scores for unvisisted neighbors
 				if !open.contains(neighbor_ref) ||
 						tentative_g < *g_score.get(neighbor_ref).expect("NO g for neighbor") {
 					came_from.insert(neighbor_ref.clone(), current_node);
 					g_score.insert(neighbor_ref.clone(), tentative_g);
 					f_score.insert(neighbor_ref.clone(), g_score.get(neighbor_ref).expect("NO g for neighbor")
-						+ self.h(neighbor_ref,&end_node));
+						+ h_scale * self.h(neighbor_ref,&end_node));
 					// insert neighbor into open set
 					if !open.contains(neighbor_ref) {
 						open.insert(neighbor_ref.clone());
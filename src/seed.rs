@@ -0,0 +1,106 @@
+use std::fmt;
+use rand::{Rng, SeedableRng, XorShiftRng, thread_rng};
+
+/// Independent random streams derived from one seed, so that e.g. a change
+/// to monster placement doesn't reshuffle the layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Layout = 1,
+    Monsters = 2,
+    Loot = 3,
+    Terrain = 4,
+    Locks = 5,
+    Secrets = 6,
+    Hazards = 7,
+}
+
+/// A run seed. Any string works ("CRIMSON-OTTER-42", "123", ...); it is
+/// normalized and hashed, so similar codes still give unrelated dungeons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Seed {
+    code: String,
+    hash: u64,
+}
+
+static ADJECTIVES: [&'static str; 16] = [
+    "AMBER", "BRAVE", "CRIMSON", "DUSTY", "EMERALD", "FERAL", "GLOOMY", "HOLLOW",
+    "IVORY", "JADED", "MOSSY", "OBSIDIAN", "RUSTY", "SILENT", "VELVET", "WILD",
+];
+
+static NOUNS: [&'static str; 16] = [
+    "BADGER", "CANDLE", "DRAGON", "FALCON", "GOBLIN", "HARE", "LANTERN", "MOLE",
+    "NEWT", "OTTER", "RAVEN", "SKULL", "TOAD", "VIPER", "WOLF", "WYRM",
+];
+
+impl Seed {
+    pub fn new(code: &str) -> Seed {
+        let code = code.trim().to_uppercase();
+        let hash = splitmix64(fnv1a(code.as_bytes()));
+        Seed { code: code, hash: hash }
+    }
+
+    /// A fresh, readable seed like "MOSSY-RAVEN-17".
+    pub fn random() -> Seed {
+        let rng = &mut thread_rng();
+        let code = format!("{}-{}-{}",
+                ADJECTIVES[rng.gen_range(0, ADJECTIVES.len())],
+                NOUNS[rng.gen_range(0, NOUNS.len())],
+                rng.gen_range(0, 100));
+        Seed::new(&code)
+    }
+
+    /// The normalized code, for showing to players.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// An RNG for one subsystem of one floor.
+    pub fn rng(&self, floor: u32, stream: Stream) -> XorShiftRng {
+        let mut state = self.hash
+                ^ splitmix64(((floor as u64) << 8) | stream as u64);
+        let a = splitmix64_next(&mut state);
+        let b = splitmix64_next(&mut state);
+        let mut words = [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32];
+        // xorshift can't start from all zeroes
+        if words.iter().all(|&w| w == 0) {
+            words[0] = 1;
+        }
+        XorShiftRng::from_seed(words)
+    }
+}
+
+impl From<u32> for Seed {
+    fn from(n: u32) -> Seed {
+        Seed::new(&n.to_string())
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for &b in bytes.iter() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// SplitMix64 finalizer; spreads similar inputs over the whole range.
+pub fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn splitmix64_next(state: &mut u64) -> u64 {
+    let out = splitmix64(*state);
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    out
+}
@@ -3,20 +3,23 @@ use std::sync::mpsc::RecvError;
 use std::sync::mpsc::TryRecvError;
 use std::sync::mpsc::TryRecvError::*;
 use graph::Graph;
-use search::{SearchStrategy,AStarSearch};
-use std::sync::{Arc,RwLock};
+use search::{SearchStrategy,AStarSearch,CostProfile};
+use std::sync::Arc;
 use std::thread;
 
 struct Problem {
 	id: usize,
-	graph: Arc<RwLock<Graph>>,
+	graph: Arc<Graph>,
 	start: (isize,isize),
 	end: (isize,isize),
+	profile: CostProfile,
 }
 
 pub struct Solution {
 	pub id: usize,
 	pub path: Option<Vec<(isize,isize)>>,
+	/// version of the graph snapshot the path was found on
+	pub graph_version: u64,
 }
 
 pub struct Solver {
@@ -40,12 +43,12 @@ impl Solver {
 				match prob_recv.recv() {
 					Ok(maybe_problem) => match maybe_problem {
 						Some(problem) => {
+							// the snapshot can't change under us; edits go to a new copy
 							let id = problem.id;
-							let path = search.solve(
-								&*problem.graph.read().ok()
-								.expect("threading sucks"),problem.start,
-								problem.end);
-							soln_send.send( Solution { id: id, path: path } );
+							let path = search.solve_with(&*problem.graph,
+								problem.start, problem.end, &problem.profile);
+							soln_send.send( Solution { id: id, path: path,
+								graph_version: problem.graph.version() } );
 						}
 						None => break,
 					},
@@ -61,12 +64,14 @@ impl Solver {
 			count: 0,
 		}
 	}
-	pub fn queue_solve(&mut self, id: usize, graph: Arc<RwLock<Graph>>, start: (isize,isize), end: (isize,isize)) {
+	pub fn queue_solve(&mut self, id: usize, graph: Arc<Graph>, start: (isize,isize), end: (isize,isize),
+			profile: CostProfile) {
 		let p = Problem{
 			id: id,
 			graph: graph,
 			start: start,
 			end: end,
+			profile: profile,
 		};
 		self.prob_send.send(Some(p));
 		self.count += 1;
@@ -0,0 +1,122 @@
+// dungeon statistics for comparing generator tunings
+
+use generator::{Dungeon,Monster};
+use validate::{validate,dead_end_count,is_passable};
+
+#[derive(Clone,RustcEncodable)]
+pub struct DungeonStats {
+	pub seed: u32,
+	pub rooms: usize,
+	pub halls: usize,
+	pub path_length: usize,
+	pub monsters: usize,
+	pub floor_ratio: f32,
+	pub dead_ends: usize,
+	pub problems: usize,
+	pub gen_ms: f64,
+	pub error: Option<String>,
+}
+
+/// min/mean/median/max of one column
+#[derive(Clone,RustcEncodable)]
+pub struct Distribution {
+	pub name: String,
+	pub min: f64,
+	pub mean: f64,
+	pub median: f64,
+	pub max: f64,
+}
+
+#[derive(Clone,RustcEncodable)]
+pub struct Summary {
+	pub count: usize,
+	pub failures: usize,
+	pub invalid: usize,
+	pub columns: Vec<Distribution>,
+}
+
+pub static CSV_HEADER: &'static str =
+	"seed,rooms,halls,path_length,monsters,floor_ratio,dead_ends,problems,gen_ms,error";
+
+impl DungeonStats {
+
+	pub fn measure(seed: u32, d: &Dungeon, gen_ms: f64) -> DungeonStats {
+		let passable = d.tiles.iter().filter(|t| is_passable(t.t)).count();
+		DungeonStats {
+			seed: seed,
+			rooms: d.rooms.iter().filter(|r| !r.hall).count(),
+			halls: d.rooms.iter().filter(|r| r.hall).count(),
+			path_length: d.path_length(),
+			monsters: d.tiles.iter().filter(|t| match t.e {
+				Some(Monster(_)) => true,
+				_ => false
+			}).count(),
+			floor_ratio: passable as f32 / d.tiles.len() as f32,
+			dead_ends: dead_end_count(d),
+			problems: validate(d).len(),
+			gen_ms: gen_ms,
+			error: None,
+		}
+	}
+
+	pub fn failed(seed: u32, error: String, gen_ms: f64) -> DungeonStats {
+		DungeonStats {
+			seed: seed,
+			rooms: 0,
+			halls: 0,
+			path_length: 0,
+			monsters: 0,
+			floor_ratio: 0.0,
+			dead_ends: 0,
+			problems: 0,
+			gen_ms: gen_ms,
+			error: Some(error),
+		}
+	}
+
+	pub fn to_csv(&self) -> String {
+		format!("{},{},{},{},{},{:.4},{},{},{:.3},{}",
+			self.seed,self.rooms,self.halls,self.path_length,self.monsters,
+			self.floor_ratio,self.dead_ends,self.problems,self.gen_ms,
+			self.error.as_ref().map_or(String::new(), |e| format!("\"{}\"",e.replace("\"","'"))))
+	}
+
+}
+
+pub fn summarize(stats: &[DungeonStats]) -> Summary {
+	let ok: Vec<&DungeonStats> = stats.iter().filter(|s| s.error.is_none()).collect();
+	let column = |name: &str, f: &Fn(&DungeonStats) -> f64| -> Distribution {
+		distribution(name, ok.iter().map(|s| f(*s)).collect())
+	};
+	Summary {
+		count: stats.len(),
+		failures: stats.len() - ok.len(),
+		invalid: ok.iter().filter(|s| s.problems > 0).count(),
+		columns: vec![
+			column("rooms", &|s| s.rooms as f64),
+			column("halls", &|s| s.halls as f64),
+			column("path_length", &|s| s.path_length as f64),
+			column("monsters", &|s| s.monsters as f64),
+			column("floor_ratio", &|s| s.floor_ratio as f64),
+			column("dead_ends", &|s| s.dead_ends as f64),
+			column("gen_ms", &|s| s.gen_ms),
+		],
+	}
+}
+
+fn distribution(name: &str, mut values: Vec<f64>) -> Distribution {
+	values.sort_by(|a,b| a.partial_cmp(b).expect("NaN in dungeon stats"));
+	let n = values.len();
+	let (min, max, mean, median) = if n == 0 {
+		(0.0, 0.0, 0.0, 0.0)
+	} else {
+		let sum = values.iter().fold(0.0, |acc, v| acc + v);
+		let median = if n % 2 == 0 {
+			(values[n/2 - 1] + values[n/2]) / 2.0
+		} else {
+			values[n/2]
+		};
+		(values[0], values[n-1], sum / n as f64, median)
+	};
+	Distribution { name: name.into(), min: min, mean: mean, median: median, max: max }
+}
@@ -0,0 +1,125 @@
+use rustc_serialize::json;
+use sfml::graphics::rc::Sprite;
+use gfx::AnimSetOptions;
+use animation::{Animation,AnimController,AnimState,AnimEvent};
+
+// a swing that lands on its third frame, and a hurt clip long enough to
+// cut it off halfway
+static ANIM_SET: &'static str = r#"{
+	"tile": [16, 16],
+	"clips": {
+		"idle": { "frames": [[0, 0]], "durations": [1.0] },
+		"walk": { "frames": [[0, 0], [1, 0]], "durations": [0.125] },
+		"attack": { "frames": [[0, 1], [1, 1], [2, 1], [3, 1]], "durations": [0.1], "mode": "once",
+			"events": { "hit": [2] } },
+		"hurt": { "frames": [[0, 2], [1, 2]], "durations": [0.15], "mode": "once" }
+	},
+	"facing": { "north": "walk", "east": "walk", "south": "walk", "west": "walk" },
+	"states": {
+		"idle": { "any": "idle" },
+		"attack": { "any": "attack" },
+		"hurt": { "any": "hurt" }
+	}
+}"#;
+
+const STEP: f32 = 0.025;
+
+// usage: test anim
+// drives an animation controller through attacks and hurts without a
+// window (the sprite never gets a texture)
+pub fn main() {
+
+	let mut failed = 0;
+
+	let opts = match json::decode::<AnimSetOptions>(ANIM_SET).map_err(|e| format!("{}",e))
+			.and_then(|o| o.validate().map_err(|e| e.to_string())) {
+		Ok(opts) => opts,
+		Err(e) => { println!("Failed: {}",e); return; }
+	};
+	let sprite = match Sprite::new() {
+		Some(sprite) => sprite,
+		None => { println!("Failed: couldn't make a sprite"); return; }
+	};
+	let anim = Animation::new(&sprite, &opts);
+
+	// attack: plays out, fires `hit` once on frame 2, then back to idle
+	let mut ctrl = AnimController::new(&anim);
+	if !ctrl.set_state(AnimState::Attack) {
+		failed += 1;
+		println!("Couldn't start attacking");
+	}
+	let (events, ticks) = run_until_idle(&mut ctrl);
+	let hit = AnimEvent { name: "hit".to_string(), state: AnimState::Attack, frame: 2 };
+	if events != vec![hit] {
+		failed += 1;
+		println!("Attack fired {:?}, expected one hit on frame 2",events);
+	}
+	// 4 frames of 0.1s
+	match ticks {
+		Some(t) if (t as f32 * STEP - 0.4).abs() < STEP * 1.5 => {}
+		_ => {
+			failed += 1;
+			println!("Attack went back to idle after {:?} ticks, expected about {}",ticks,0.4/STEP);
+		}
+	}
+
+	// hurt cuts an attack off before it lands, and can't be cut off by one
+	let mut ctrl = AnimController::new(&anim);
+	ctrl.set_state(AnimState::Attack);
+	ctrl.update(0.15);
+	ctrl.take_events();
+	if !ctrl.set_state(AnimState::Hurt) {
+		failed += 1;
+		println!("Hurt didn't interrupt the attack");
+	}
+	if ctrl.set_state(AnimState::Attack) {
+		failed += 1;
+		println!("Attack interrupted hurt");
+	}
+	if ctrl.state() != AnimState::Hurt {
+		failed += 1;
+		println!("In {:?} after being hurt",ctrl.state());
+	}
+	let (events, ticks) = run_until_idle(&mut ctrl);
+	if !events.is_empty() {
+		failed += 1;
+		println!("The interrupted attack still fired {:?}",events);
+	}
+	if ticks.is_none() {
+		failed += 1;
+		println!("Never went back to idle after being hurt");
+	}
+
+	// once idle, attacking works again
+	if !ctrl.set_state(AnimState::Attack) {
+		failed += 1;
+		println!("Couldn't attack after recovering");
+	}
+
+	// no death clip: death holds the frame and is final
+	let mut ctrl = AnimController::new(&anim);
+	if !ctrl.set_state(AnimState::Death) || !ctrl.is_done_dying() {
+		failed += 1;
+		println!("Death without a clip should be immediate");
+	}
+	if ctrl.set_state(AnimState::Idle) {
+		failed += 1;
+		println!("Came back from the dead");
+	}
+
+	println!("{} problem(s).",failed);
+}
+
+// updates in fixed steps for up to two seconds, collecting events, until
+// the controller drops back to idle; returns the ticks it took
+fn run_until_idle(ctrl: &mut AnimController) -> (Vec<AnimEvent>, Option<usize>) {
+	let mut events = Vec::new();
+	for tick in 1..(2.0 / STEP) as usize + 1 {
+		ctrl.update(STEP);
+		events.extend(ctrl.take_events());
+		if ctrl.state() == AnimState::Idle {
+			return (events, Some(tick));
+		}
+	}
+	(events, None)
+}
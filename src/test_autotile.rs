@@ -0,0 +1,97 @@
+use std::process;
+use std::collections::{HashMap,HashSet};
+use gfx::{SpriteRegistry,SpriteSetOptions};
+use gfx::autotile::{self,N,NE,E,SE,S,SW,W,NW};
+
+const SIDES: [&'static str; 4] = ["edge_n", "edge_e", "edge_s", "edge_w"];
+const CORNERS: [&'static str; 4] = ["corner_ne", "corner_se", "corner_sw", "corner_nw"];
+
+// floor tiles and the wall pieces they should show
+struct Case {
+	what: &'static str,
+	mask: u8,
+	pieces: &'static [&'static str],
+}
+
+static CASES: [Case; 6] = [
+	Case { what: "open floor", mask: 0, pieces: &[] },
+	Case { what: "wall to the north", mask: N, pieces: &["edge_n"] },
+	Case { what: "lone pillar to the north-east", mask: NE, pieces: &["corner_ne"] },
+	Case { what: "corner hidden by its sides", mask: N | NE | E, pieces: &["edge_n", "edge_e"] },
+	Case { what: "corner beside one side", mask: S | SE, pieces: &["edge_s"] },
+	Case { what: "four pillars", mask: NE | SE | SW | NW,
+		pieces: &["corner_ne", "corner_se", "corner_sw", "corner_nw"] },
+];
+
+// usage: test autotile
+// checks the wall masks reduce to the 47 blob shapes, that floor tiles pick
+// the right edge and corner pieces, and that the shipped wall set has them;
+// exits with 1 on any problem
+pub fn main() {
+
+	let mut failed = 0;
+
+	let shapes: HashSet<u8> = (0..256).map(|mask| autotile::reduce(mask as u8)).collect();
+	if shapes.len() != 47 {
+		failed += 1;
+		println!("{} distinct wall shapes, expected 47",shapes.len());
+	}
+	for mask in 0..256 {
+		let mask = mask as u8;
+		if autotile::reduce(autotile::reduce(mask)) != autotile::reduce(mask) {
+			failed += 1;
+			println!("Reducing mask {} twice changed it",mask);
+		}
+	}
+
+	// every piece at its own position, so they can be told apart
+	let mut variants = HashMap::new();
+	for (i, name) in SIDES.iter().chain(CORNERS.iter()).enumerate() {
+		variants.insert(name.to_string(), (i as u32, 0));
+	}
+	let opts = SpriteSetOptions { variants: Some(variants.clone()), ..Default::default() };
+	for case in CASES.iter() {
+		let found: Vec<(u32,u32)> = autotile::edge_variants(&opts, case.mask).into_iter().map(|(pos,_)| pos).collect();
+		let expected: Vec<(u32,u32)> = case.pieces.iter().map(|&name| variants[name]).collect();
+		if found != expected {
+			failed += 1;
+			println!("{}: got pieces {:?}, expected {:?}",case.what,found,expected);
+		}
+	}
+	// corners lean toward their wall
+	let all_corners = NE | SE | SW | NW;
+	let dirs: Vec<(isize,isize)> = autotile::edge_variants(&opts, all_corners).into_iter().map(|(_,dir)| dir).collect();
+	if dirs != vec![(1,-1),(1,1),(-1,1),(-1,-1)] {
+		failed += 1;
+		println!("Corner pieces nudged {:?}",dirs);
+	}
+
+	// the shipped set draws something for every wall and every border
+	match SpriteRegistry::load_default() {
+		Ok(sprites) => match sprites.get("wall") {
+			Some(walls) => {
+				for mask in 0..256 {
+					if autotile::wall_variants(walls, mask as u8).is_empty() {
+						failed += 1;
+						println!("wall.json has nothing for a wall with mask {}",mask);
+						break;
+					}
+				}
+				let names: HashSet<String> = walls.variants.as_ref().map(|v| v.keys().cloned().collect()).unwrap_or(HashSet::new());
+				for name in SIDES.iter().chain(CORNERS.iter()) {
+					if !names.contains(*name) {
+						failed += 1;
+						println!("wall.json has no `{}` variant",name);
+					}
+				}
+			}
+			None => { failed += 1; println!("No wall sprite set"); }
+		},
+		Err(e) => { failed += 1; println!("Couldn't load sprites: {}",e); }
+	}
+
+	println!("{} case(s), {} problem(s).",CASES.len(),failed);
+	if failed > 0 {
+		process::exit(1);
+	}
+}
@@ -0,0 +1,70 @@
+use std::process;
+use config::{Config,CONFIG_FILE};
+
+// usage: test config
+// loads ./config.json (if there is one), then checks that bad settings fall
+// back to their defaults with a warning instead of stopping the game
+pub fn main() {
+
+	let (config, warnings) = Config::load_quietly(CONFIG_FILE);
+	for warning in warnings.iter() {
+		println!("Warning: {}",warning);
+	}
+	println!("{:?}",config);
+
+	let mut passed = Vec::new();
+
+	let all = r#"{ "window": [1024, 768], "new_window": [640, 640], "zoom_levels": [0.5, 1, 2],
+		"solver_threads": 2, "hero_speed": 6 }"#;
+	let everything = Config {
+		window: (1024, 768),
+		new_window: (640, 640),
+		zoom_levels: vec![0.5, 1., 2.],
+		solver_threads: 2,
+		hero_speed: 6.0,
+	};
+	passed.push(check("everything", all, everything.clone(), 0));
+
+	// the tile size belongs to the art, not the player
+	let tiles = r#"{ "window": [1024, 768], "new_window": [640, 640], "zoom_levels": [0.5, 1, 2],
+		"tile_size": 32, "solver_threads": 2, "hero_speed": 6 }"#;
+	passed.push(check("tile size", tiles, everything, 1));
+
+	// one warning listing everything that's missing
+	passed.push(check("empty", "{}", Config::default(), 1));
+	passed.push(check("not json", "window = 800x600", Config::default(), 1));
+	passed.push(check("not an object", "[800, 600]", Config::default(), 1));
+
+	let bad = r#"{ "window": [800], "new_window": [0, 800], "zoom_levels": [],
+		"tile_size": -16, "solver_threads": 0, "hero_speed": "fast", "volume": 11 }"#;
+	passed.push(check("bad values", bad, Config::default(), 7));
+
+	let some = r#"{ "window": [1280, 720], "tile_size": "big", "solver_threads": 8,
+		"new_window": [800, 800], "zoom_levels": [1, 2], "hero_speed": 4 }"#;
+	passed.push(check("some bad", some, Config {
+		window: (1280, 720),
+		zoom_levels: vec![1., 2.],
+		solver_threads: 8,
+		..Config::default()
+	}, 1));
+
+	let failed = passed.iter().filter(|&&ok| !ok).count();
+	if failed > 0 {
+		println!("{} config check(s) failed.",failed);
+		process::exit(1);
+	}
+	println!("All config checks passed.");
+}
+
+// parses `text` and compares it with what should come out
+fn check(name: &str, text: &str, expected: Config, warning_count: usize) -> bool {
+	let (config, warnings) = Config::parse(name,text);
+	if config == expected && warnings.len() == warning_count {
+		return true;
+	}
+	println!("{}: got {:?}",name,config);
+	for warning in warnings.iter() {
+		println!("    {}",warning);
+	}
+	false
+}
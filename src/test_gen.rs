@@ -1,7 +1,8 @@
 extern crate rand;
 
 use std::io::{stdin,Read,BufReader,BufRead};
-use generator::generate_default;
+use generator::{Features,generate_default};
+use seed::Seed;
 use std::str::FromStr;
 
 fn uint_from_reader<T: Read>(reader: &mut BufReader<T>) -> Option<usize> {
@@ -13,6 +14,10 @@ fn uint_from_reader<T: Read>(reader: &mut BufReader<T>) -> Option<usize> {
 
 pub fn main() {
 
+	let features = match Features::load_default() {
+		Ok(features) => features,
+		Err(e) => { println!("{}",e); return; }
+	};
 	let mut reader = BufReader::new(stdin());
 
 	println!("How many dungeons to generate: ");
@@ -29,11 +34,14 @@ pub fn main() {
 	for i in 0..count {
 		println!("Generating dungeon {}...",i);
 
-		let mut d = generate_default(seed+i as u32);
+		let mut d = match generate_default(&Seed::from(seed+i as u32),&features) {
+			Ok(d) => d,
+			Err(e) => { println!("Generation failed: {}",e); continue; }
+		};
 		total_w += d.width() as f32;
 		total_h += d.height() as f32;
 		total += 1.0;
-		d.shrink(); // this doesn't happen normally because of coordinate screwups
+		d.crop();
 		dungeons.push(d);
 	}
 	println!("Average size across {} dungeons was {}x{}.",count, (total_w/total).round(), (total_h/total).round());
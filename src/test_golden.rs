@@ -0,0 +1,174 @@
+// golden-master checks: a refactor must not change what a seed produces
+
+use std::collections::BTreeMap;
+use std::fs::{self,File};
+use std::io::{Read,Write};
+use std::process;
+use rustc_serialize::json;
+use generator::{self,generate_with,Features,Monster,Door};
+use seed::{Seed,fnv1a};
+use test_dungeon;
+
+static GOLDEN_DIR: &'static str = "./res/golden/";
+static FINGERPRINTS: &'static str = "fingerprints.json";
+const GENERATOR_SEEDS: [u32; 6] = [0, 1, 2, 42, 123, 4414];
+const WORLD_OFFSETS: [u32; 3] = [0, 1, 2];
+const MAX_DIFF_LINES: usize = 40;
+
+struct Snapshot {
+	name: String,
+	text: String,
+}
+
+impl Snapshot {
+	fn fingerprint(&self) -> String {
+		format!("{:016x}",fnv1a(self.text.as_bytes()))
+	}
+	fn path(&self) -> String {
+		format!("{}{}.txt",GOLDEN_DIR,self.name)
+	}
+}
+
+/// `golden`: compare against the checked-in fingerprints
+pub fn main(features: &Features) {
+	let expected = load_fingerprints();
+	let mut failed = 0;
+	let mut shown_diff = false;
+	for snap in snapshots(features).iter() {
+		let actual = snap.fingerprint();
+		match expected.get(&snap.name) {
+			None => {
+				failed += 1;
+				println!("{}: no fingerprint recorded (run `golden --update`)",snap.name);
+			}
+			Some(fp) if *fp == actual => println!("{}: ok",snap.name),
+			Some(fp) => {
+				failed += 1;
+				println!("{}: MISMATCH (expected {}, got {})",snap.name,fp,actual);
+				// one diff is enough to see what went wrong
+				if !shown_diff {
+					shown_diff = true;
+					print_diff(snap);
+				}
+			}
+		}
+	}
+	if failed > 0 {
+		println!("{} golden snapshot(s) failed.",failed);
+		process::exit(1);
+	}
+	println!("All golden snapshots match.");
+}
+
+/// `golden --update`: record the current output as the new golden master
+pub fn update(features: &Features) {
+	if let Err(e) = fs::create_dir_all(GOLDEN_DIR) {
+		println!("Couldn't create {}: {}",GOLDEN_DIR,e);
+		process::exit(1);
+	}
+	let mut fingerprints = BTreeMap::new();
+	for snap in snapshots(features).iter() {
+		let written = File::create(&snap.path())
+			.and_then(|mut f| f.write_all(snap.text.as_bytes()));
+		if let Err(e) = written {
+			println!("Couldn't write {}: {}",snap.path(),e);
+			process::exit(1);
+		}
+		fingerprints.insert(snap.name.clone(),snap.fingerprint());
+		println!("{}: {}",snap.name,snap.fingerprint());
+	}
+	let path = format!("{}{}",GOLDEN_DIR,FINGERPRINTS);
+	let text = format!("{}\n",json::as_pretty_json(&fingerprints));
+	match File::create(&path).and_then(|mut f| f.write_all(text.as_bytes())) {
+		Ok(_) => println!("Wrote {}",path),
+		Err(e) => { println!("Couldn't write {}: {}",path,e); process::exit(1); }
+	}
+}
+
+fn snapshots(features: &Features) -> Vec<Snapshot> {
+	let mut snaps = Vec::new();
+	let params = generator::DungeonParams::default();
+	for &seed in GENERATOR_SEEDS.iter() {
+		let text = match generate_with(&Seed::from(seed),&params,features) {
+			Ok(d) => describe(&d,features),
+			Err(e) => format!("error: {}\n",e),
+		};
+		snaps.push(Snapshot { name: format!("generator-{}",seed), text: text });
+	}
+	for &offset in WORLD_OFFSETS.iter() {
+		let text = match test_dungeon::test(offset) {
+			Ok(d) => d.to_ascii(),
+			Err(e) => format!("error: {}\n",e),
+		};
+		snaps.push(Snapshot { name: format!("world-{}",offset), text: text });
+	}
+	snaps
+}
+
+// the map plus everything the map doesn't show: monster kinds, door
+// states, and the terrain and hazard layers
+fn describe(d: &generator::Dungeon, features: &Features) -> String {
+	let mut text = d.to_ascii();
+	text.push_str(&format!("start {:?}\nend {:?}\npath {}\n",
+		d.start_coords,d.end_coords,d.path_length()));
+	for tile in d.get_tile_vector().iter() {
+		match tile.e {
+			Some(Monster(num)) => text.push_str(&format!("monster {} {} {}\n",tile.x,tile.y,num)),
+			_ => {}
+		}
+		match tile.t {
+			Door(state) => text.push_str(&format!("door {} {} {:?}\n",tile.x,tile.y,state)),
+			_ => {}
+		}
+		if let Some(name) = d.terrain_name(tile) {
+			let layer = if features.hazards.iter().any(|&(ref h,_)| h == name) { "hazard" } else { "terrain" };
+			text.push_str(&format!("{} {} {} {}\n",layer,tile.x,tile.y,name));
+		}
+	}
+	text
+}
+
+fn load_fingerprints() -> BTreeMap<String,String> {
+	let path = format!("{}{}",GOLDEN_DIR,FINGERPRINTS);
+	let mut s = String::new();
+	match File::open(&path).and_then(|mut f| f.read_to_string(&mut s)) {
+		Ok(_) => json::decode(&s).unwrap_or_else(|e| {
+			println!("Couldn't parse {}: {}",path,e);
+			BTreeMap::new()
+		}),
+		Err(e) => {
+			println!("Couldn't read {}: {}",path,e);
+			BTreeMap::new()
+		}
+	}
+}
+
+fn print_diff(snap: &Snapshot) {
+	let mut old = String::new();
+	if File::open(&snap.path()).and_then(|mut f| f.read_to_string(&mut old)).is_err() {
+		println!("    (no snapshot at {} to diff against)",snap.path());
+		return;
+	}
+	println!("--- {} (golden)",snap.path());
+	println!("+++ {} (current)",snap.name);
+	let old_lines: Vec<&str> = old.lines().collect();
+	let new_lines: Vec<&str> = snap.text.lines().collect();
+	let mut shown = 0;
+	for i in 0..::std::cmp::max(old_lines.len(),new_lines.len()) {
+		let a = old_lines.get(i).cloned().unwrap_or("");
+		let b = new_lines.get(i).cloned().unwrap_or("");
+		if a == b { continue; }
+		if shown >= MAX_DIFF_LINES {
+			println!("    ... more differences not shown");
+			break;
+		}
+		shown += 1;
+		// mark the columns that changed
+		let marks: String = (0..::std::cmp::max(a.len(),b.len()))
+			.map(|c| if a.as_bytes().get(c) == b.as_bytes().get(c) { ' ' } else { '^' })
+			.collect();
+		println!("{:>4} - {}",i,a);
+		println!("{:>4} + {}",i,b);
+		println!("       {}",marks);
+	}
+}
@@ -0,0 +1,111 @@
+use rustc_serialize::json;
+use input::{Input,InputScript,Bindings,Action,StepKind,ACTIONS,BINDINGS_FILE,key_name};
+use util::PI;
+
+// usage: test input
+// checks res/dat/bindings.json, then plays a script through the input layer
+// without a window
+pub fn main() {
+
+	let mut failed = 0;
+
+	let bindings = match Bindings::load(BINDINGS_FILE) {
+		Ok(bindings) => bindings,
+		Err(e) => { println!("Failed: {}",e); return; }
+	};
+	for &(name, action) in ACTIONS.iter() {
+		let mut keys: Vec<String> = bindings.keys(action).iter()
+			.map(|&k| key_name(k).unwrap_or("?").to_string())
+			.collect();
+		keys.extend(bindings.buttons(action).iter().map(|b| format!("Button{}",b)));
+		if keys.is_empty() {
+			println!("    {:<12} (unbound)",name);
+		} else {
+			println!("    {:<12} {}",name,keys.join(", "));
+		}
+		for &k in bindings.keys(action).iter() {
+			let others: Vec<&str> = bindings.actions(k).iter()
+				.filter(|&&a| a != action)
+				.map(|a| a.name())
+				.collect();
+			if !others.is_empty() {
+				println!("Warning: {} is also bound to {}",key_name(k).unwrap_or("?"),others.join(", "));
+			}
+		}
+	}
+
+	// walk right for a bit, swinging once on the way
+	let script = InputScript::new()
+		.step(0, Action::MoveRight, StepKind::Down)
+		.step(2, Action::Attack, StepKind::Tap)
+		.step(3, Action::MoveUp, StepKind::Down)
+		.step(5, Action::MoveRight, StepKind::Up)
+		.step(5, Action::MoveUp, StepKind::Up);
+	let held_right = [true, true, true, true, true, false];
+	let held_up = [false, false, false, true, true, false];
+
+	let mut input = Input::new(Bindings::default());
+	input.record();
+	input.play(script.clone());
+	for tick in 0..script.length() as usize {
+		input.update();
+		let pressed = input.take_pressed();
+		if input.is_held(Action::MoveRight) != held_right[tick]
+				|| input.is_held(Action::MoveUp) != held_up[tick] {
+			failed += 1;
+			println!("Tick {}: wrong actions held",tick);
+		}
+		let want: Vec<Action> = if tick == 2 { vec![Action::Attack] } else { vec![] };
+		if pressed != want {
+			failed += 1;
+			println!("Tick {}: pressed {:?}, expected {:?}",tick,pressed,want);
+		}
+	}
+	if input.is_playing() {
+		failed += 1;
+		println!("Script still playing after its last step");
+	}
+
+	// what was recorded should replay the same way
+	let mut expected = script.clone();
+	expected.sort();
+	match input.take_recording() {
+		Some(ref rec) if *rec == expected => {}
+		rec => {
+			failed += 1;
+			println!("Recording doesn't match the script: {:?}",rec);
+		}
+	}
+	// analog: a nudge inside the dead zone, the stick halfway to the
+	// bottom right, then the keys the same way
+	let mut script = InputScript::new();
+	script.push_stick(0, 0.1, 0.0);
+	script.push_stick(1, 0.6, 0.6);
+	script.push_stick(2, 0.0, 0.0);
+	script.push(2, Action::MoveRight, StepKind::Down);
+	script.push(2, Action::MoveDown, StepKind::Down);
+	let expected = [None, Some((PI/4.0, 0.81)), Some((PI/4.0, 1.0))];
+	let mut input = Input::new(Bindings::default());
+	input.play(script.clone());
+	for (tick, want) in expected.iter().enumerate() {
+		input.update();
+		let close = match (input.movement(), *want) {
+			(None, None) => true,
+			(Some((a, s)), Some((wa, ws))) => (a - wa).abs() < 0.01 && (s - ws).abs() < 0.01,
+			_ => false
+		};
+		if !close {
+			failed += 1;
+			println!("Tick {}: moving {:?}, expected {:?}",tick,input.movement(),want);
+		}
+	}
+
+	let round_trip: Option<InputScript> = json::encode(&script).ok()
+		.and_then(|s| json::decode(&s).ok());
+	if round_trip.as_ref() != Some(&script) {
+		failed += 1;
+		println!("Script didn't survive being saved and loaded");
+	}
+
+	println!("{} problem(s).",failed);
+}
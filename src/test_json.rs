@@ -19,6 +19,10 @@ pub fn main() {
             name: "window".into(),
             wall: true,
             opaque: false,
+            cost: None,
+            hazard: None,
+            min_floor: None,
+            tint: None,
         };
 
         // SpriteInfo {
@@ -0,0 +1,94 @@
+use std::path::Path;
+use std::process;
+use audio::{AudioBackend,SoundManager,SOUNDS_FILE,DEFAULT_VOICES,DEFAULT_RANGE,load_sound_defs};
+use util::get_snd_path;
+
+// sounds that never finish, so the voice limit can be seen without a device
+struct HeldBackend {
+	playing: usize,
+}
+
+impl AudioBackend for HeldBackend {
+	fn load(&mut self, path: &str) -> bool { Path::new(path).is_file() }
+	fn play(&mut self, _: &str, _: f32) { self.playing += 1; }
+	fn voices(&mut self) -> usize { self.playing }
+	fn play_music(&mut self, path: &str, _: f32) -> bool { Path::new(path).is_file() }
+	fn stop_music(&mut self) {}
+}
+
+// usage: test sound
+// checks res/snd/sounds.json without opening an audio device; exits with 1
+// on any problem
+pub fn main() {
+
+	let path = get_snd_path(SOUNDS_FILE);
+	let defs = match load_sound_defs(&path) {
+		Ok(defs) => defs,
+		Err(e) => { println!("Failed: {}",e); process::exit(1); }
+	};
+
+	let mut failed = 0;
+	let mut names: Vec<String> = defs.sounds.keys().cloned().collect();
+	names.sort();
+	for name in names.iter() {
+		let file = get_snd_path(&defs.sounds[name].file);
+		if !Path::new(&file).is_file() {
+			failed += 1;
+			println!("Sound `{}`: missing {}",name,file);
+		}
+	}
+	for (floor, file) in defs.music.iter() {
+		if !Path::new(&get_snd_path(file)).is_file() {
+			failed += 1;
+			println!("Music for floor {}: missing {}",floor,get_snd_path(file));
+		}
+	}
+
+	if !defs.music.contains_key("default") {
+		failed += 1;
+		println!("No `default` music, so floors without their own track are silent");
+	}
+
+	let voices = defs.voices.unwrap_or(DEFAULT_VOICES);
+	let range = names.first().and_then(|n| defs.sounds[n].range).unwrap_or(DEFAULT_RANGE);
+	let mut sounds = SoundManager::new(defs, Box::new(HeldBackend { playing: 0 }));
+	sounds.set_listener(0.0,0.0);
+
+	// falloff: the same sound from further and further away should get
+	// quieter, and be gone once it's out of range
+	if let Some(name) = names.first() {
+		println!("`{}` by distance (range {}):",name,range);
+		let mut last = None;
+		for dist in 0..range.ceil() as usize + 3 {
+			let vol = sounds.volume_at(name, dist as f32, 0.0);
+			println!("    {:>2} tiles: {}",dist,
+				vol.map_or("out of earshot".to_string(), |v| format!("{:.1}",v)));
+			let quieter = match (last, vol) {
+				(Some(a), Some(b)) => b < a,
+				(None, Some(_)) => dist == 0,
+				(_, None) => dist > 0,
+			};
+			if !quieter {
+				failed += 1;
+				println!("Falloff: {:?} at {} tiles after {:?}",vol,dist,last);
+			}
+			if dist as f32 >= range && vol.is_some() {
+				failed += 1;
+				println!("Falloff: still heard at {} tiles, past its range of {}",dist,range);
+			}
+			last = vol;
+		}
+
+		// voice limit: one more than allowed should be dropped
+		let played = (0..voices+1).filter(|_| sounds.play(name).is_some()).count();
+		if played != voices {
+			failed += 1;
+			println!("Voice limit: played {} of {} with {} voices",played,voices+1,voices);
+		}
+	}
+
+	println!("{} sound(s), {} problem(s).",names.len(),failed);
+	if failed > 0 {
+		process::exit(1);
+	}
+}
@@ -0,0 +1,73 @@
+use std::io::{stderr,Write};
+use std::ops::Range;
+use std::sync::mpsc::channel;
+use std::thread;
+use clock_ticks::precise_time_ns;
+use num_cpus;
+use rustc_serialize::json;
+use generator::{DungeonParams,Features,generate_with};
+use seed::Seed;
+use stats::{self,DungeonStats,CSV_HEADER};
+
+pub const DEFAULT_COUNT: u32 = 100;
+
+#[derive(RustcEncodable)]
+struct Report {
+	seeds: Vec<DungeonStats>,
+	summary: stats::Summary,
+}
+
+// usage: stats [--count=<n>] [--seed=<seed>] [--params=<file>] [--json-report]
+// prints one CSV row per seed, with the summary on stderr
+pub fn main(seeds: Range<u32>, params: &DungeonParams, features: &Features, as_json: bool) {
+
+	let params = *params;
+	let threads = num_cpus::get() as u32;
+
+	let (send,recv) = channel();
+	for t in 0..threads {
+		let send = send.clone();
+		let features = features.clone();
+		let seeds = seeds.clone();
+		thread::spawn(move || {
+			// every `threads`th seed, stopping short of overflowing
+			let mut next = seeds.start.checked_add(t);
+			while let Some(seed) = next {
+				if seed >= seeds.end { break; }
+				let start = precise_time_ns();
+				let result = generate_with(&Seed::from(seed),&params,&features);
+				let ms = (precise_time_ns() - start) as f64 / 1.0e6;
+				let s = match result {
+					Ok(d) => DungeonStats::measure(seed,&d,ms),
+					Err(e) => DungeonStats::failed(seed,format!("{}",e),ms),
+				};
+				send.send(s).ok().expect("Stats receiver hung up");
+				next = seed.checked_add(threads);
+			}
+		});
+	}
+	drop(send);
+
+	let mut results: Vec<DungeonStats> = recv.iter().collect();
+	results.sort_by(|a,b| a.seed.cmp(&b.seed));
+	let summary = stats::summarize(&results);
+
+	if as_json {
+		let report = Report { seeds: results, summary: summary };
+		println!("{}",json::as_pretty_json(&report));
+		return;
+	}
+
+	println!("{}",CSV_HEADER);
+	for s in results.iter() {
+		println!("{}",s.to_csv());
+	}
+	let mut err = stderr();
+	writeln!(err,"{} dungeons on {} threads: {} failed to generate, {} failed validation",
+		summary.count,threads,summary.failures,summary.invalid).ok();
+	writeln!(err,"{:>12} {:>10} {:>10} {:>10} {:>10}","","min","mean","median","max").ok();
+	for c in summary.columns.iter() {
+		writeln!(err,"{:>12} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+			c.name,c.min,c.mean,c.median,c.max).ok();
+	}
+}
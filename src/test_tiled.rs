@@ -0,0 +1,122 @@
+use std::fs;
+use rustc_serialize::json::Json;
+use generator::{Features, generate_default};
+use seed::Seed;
+use gfx::{SpriteRegistry, TILE_SPRITES};
+use test_dungeon;
+use tiled;
+
+const SEED: u32 = 123;
+
+pub fn main() {
+    if let Err(e) = fs::create_dir_all(tiled::MAP_DIR) {
+        println!("Couldn't create {}: {}", tiled::MAP_DIR, e);
+        return;
+    }
+
+    let sprites = match SpriteRegistry::load_default() {
+        Ok(sprites) => sprites,
+        Err(e) => { println!("Couldn't load sprites: {}", e); return; }
+    };
+    if let Err(e) = sprites.check(&TILE_SPRITES) {
+        println!("{}", e);
+        return;
+    }
+
+    let features = match Features::load_default() {
+        Ok(features) => features,
+        Err(e) => { println!("{}", e); return; }
+    };
+    let d = match generate_default(&Seed::from(SEED), &features) {
+        Ok(d) => d,
+        Err(e) => { println!("Generation failed: {}", e); return; }
+    };
+    let path = format!("{}dungeon-{}.json", tiled::MAP_DIR, SEED);
+    match tiled::save(&tiled::export_dungeon(&d, &sprites), &path) {
+        Ok(_) => println!("Wrote {}", path),
+        Err(e) => { println!("Couldn't write {}: {}", path, e); return; }
+    }
+
+    // make sure the map survives a round trip
+    match tiled::load(&path, &sprites) {
+        Ok(loaded) => {
+            let same = loaded.width() == d.width() && loaded.height() == d.height()
+                && loaded.start_coords == d.start_coords
+                && loaded.end_coords == d.end_coords
+                && loaded.tiles.iter().zip(d.tiles.iter())
+                    .all(|(a, b)| a.t == b.t && a.e.is_some() == b.e.is_some());
+            println!("Round trip {}", if same { "OK" } else { "FAILED" });
+            // water, traps and the like ride along as objects
+            let terrain = d.tiles.iter().filter(|t| t.terrain.is_some()).count();
+            let same_terrain = loaded.tiles.iter().zip(d.tiles.iter())
+                .all(|(a, b)| loaded.terrain_name(a) == d.terrain_name(b));
+            println!("Terrain round trip ({} tiles) {}", terrain,
+                    if same_terrain && terrain > 0 { "OK" } else { "FAILED" });
+        }
+        Err(e) => println!("Couldn't read {} back: {}", path, e),
+    }
+
+    // hand edits that should be turned away rather than crash or slip through
+    let exported = tiled::export_dungeon(&d, &sprites);
+    let mut zero_width = exported.clone();
+    if let Json::Object(ref mut map) = zero_width {
+        map.insert("tilewidth".into(), Json::U64(0));
+    }
+    check_rejected("Zero tilewidth", &zero_width, &sprites);
+    let off_map = edit_objects(&exported, |objects| {
+        if let Some(&mut Json::Object(ref mut o)) = objects.get_mut(0) {
+            o.insert("x".into(), Json::F64(-1.0));
+        }
+    });
+    check_rejected("Object left of the map", &off_map, &sprites);
+    // the first object is the up stairs
+    let two_starts = edit_objects(&exported, |objects| {
+        let extra = objects[0].clone();
+        objects.push(extra);
+    });
+    check_rejected("Second stairs_up", &two_starts, &sprites);
+    let unknown = edit_objects(&exported, |objects| {
+        let mut extra = objects[0].clone();
+        if let Json::Object(ref mut o) = extra {
+            o.insert("type".into(), Json::String("dragon".into()));
+        }
+        objects.push(extra);
+    });
+    check_rejected("Unknown object type", &unknown, &sprites);
+
+    match test_dungeon::test(0) {
+        Ok(w) => {
+            let path = format!("{}world-0.json", tiled::MAP_DIR);
+            match tiled::save(&tiled::export_world(&w, &sprites), &path) {
+                Ok(_) => println!("Wrote {}", path),
+                Err(e) => println!("Couldn't write {}: {}", path, e),
+            }
+        }
+        Err(e) => println!("World generation failed: {}", e),
+    }
+}
+
+// a copy of an exported map with its object list changed
+fn edit_objects<F: FnOnce(&mut Vec<Json>)>(j: &Json, edit: F) -> Json {
+    let mut j = j.clone();
+    if let Json::Object(ref mut map) = j {
+        if let Some(&mut Json::Array(ref mut layers)) = map.get_mut("layers") {
+            for layer in layers.iter_mut() {
+                if let Json::Object(ref mut layer) = *layer {
+                    if let Some(&mut Json::Array(ref mut objects)) = layer.get_mut("objects") {
+                        edit(objects);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    j
+}
+
+fn check_rejected(what: &str, j: &Json, sprites: &SpriteRegistry) {
+    match tiled::import_dungeon(j, sprites) {
+        Ok(_) => println!("{} FAILED: the map was accepted", what),
+        Err(e) => println!("{} rejected OK ({})", what, e),
+    }
+}
@@ -0,0 +1,74 @@
+use timestep::{FixedStep,DEFAULT_MAX_TICKS};
+
+const EPSILON: f64 = 1e-9;
+
+// what a clock should do with a run of frame times
+struct Case {
+	what: &'static str,
+	rate: f64,
+	max_ticks: u32,
+	frames: &'static [f64],
+	// ticks run after each frame
+	ticks: &'static [u32],
+	// at the end
+	alpha: f64,
+	dropped: f64,
+}
+
+static CASES: [Case; 6] = [
+	Case { what: "one tick and a half", rate: 60.0, max_ticks: DEFAULT_MAX_TICKS,
+		frames: &[0.025], ticks: &[1], alpha: 0.5, dropped: 0.0 },
+	Case { what: "short frames add up", rate: 60.0, max_ticks: DEFAULT_MAX_TICKS,
+		frames: &[0.01, 0.01, 0.01], ticks: &[0, 1, 0], alpha: 0.8, dropped: 0.0 },
+	Case { what: "a slow frame is capped", rate: 60.0, max_ticks: DEFAULT_MAX_TICKS,
+		frames: &[0.1], ticks: &[5], alpha: 0.0, dropped: 1.0 / 60.0 },
+	Case { what: "a lower cap drops more", rate: 60.0, max_ticks: 2,
+		frames: &[0.1, 0.025], ticks: &[2, 1], alpha: 0.5, dropped: 4.0 / 60.0 },
+	Case { what: "time going backwards is ignored", rate: 60.0, max_ticks: DEFAULT_MAX_TICKS,
+		frames: &[0.01, -1.0], ticks: &[0, 0], alpha: 0.6, dropped: 0.0 },
+	Case { what: "other rates", rate: 20.0, max_ticks: DEFAULT_MAX_TICKS,
+		frames: &[0.125, 0.1], ticks: &[2, 2], alpha: 0.5, dropped: 0.0 },
+];
+
+// usage: test timestep
+// feeds frame times through the fixed-step clock and checks the ticks,
+// the catch-up limit and the interpolation alpha
+pub fn main() {
+
+	let mut failed = 0;
+
+	for case in CASES.iter() {
+		let mut clock = FixedStep::new(case.rate).max_ticks(case.max_ticks);
+		let ticks: Vec<u32> = case.frames.iter().map(|&f| clock.advance(f)).collect();
+		if &ticks[..] != case.ticks {
+			failed += 1;
+			println!("{}: ran {:?} ticks, expected {:?}",case.what,ticks,case.ticks);
+		}
+		if (clock.alpha() - case.alpha).abs() > EPSILON {
+			failed += 1;
+			println!("{}: alpha {}, expected {}",case.what,clock.alpha(),case.alpha);
+		}
+		if (clock.dropped() - case.dropped).abs() > EPSILON {
+			failed += 1;
+			println!("{}: dropped {}s, expected {}s",case.what,clock.dropped(),case.dropped);
+		}
+	}
+
+	// over many frames, ticks run plus time dropped plus what's left over
+	// should account for every second fed in
+	let mut clock = FixedStep::default();
+	let frames = [0.016, 0.017, 0.2, 0.001, 0.033, 0.5, 0.0166];
+	let ran = frames.iter().fold(0, |n, &f| n + clock.advance(f));
+	let total = frames.iter().fold(0.0, |sum, &f| sum + f);
+	let accounted = ran as f64 * clock.tick() + clock.dropped() + clock.alpha() * clock.tick();
+	if (accounted - total).abs() > EPSILON {
+		failed += 1;
+		println!("Lost time: fed {}s, accounted for {}s",total,accounted);
+	}
+	if clock.alpha() < 0.0 || clock.alpha() >= 1.0 {
+		failed += 1;
+		println!("Alpha {} should be from 0 up to 1",clock.alpha());
+	}
+
+	println!("{} case(s), {} problem(s).",CASES.len() + 1,failed);
+}
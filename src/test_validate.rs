@@ -0,0 +1,42 @@
+use std::io::{stderr,Write};
+use generator::{DungeonParams,Features,generate_with};
+use validate::validate;
+use cli::Seeds;
+
+pub const DEFAULT_COUNT: u32 = 100;
+
+// usage: validate [--count=<n>] [--seed=<seed>] [--params=<file>]
+// an error if any seed fails
+pub fn main(seeds: Seeds, params: &DungeonParams, features: &Features) -> Result<(), String> {
+
+	let count = seeds.len();
+	match seeds {
+		Seeds::Numbered(ref range) => println!("Validating {} seed(s) starting at {}...",count,range.start),
+		Seeds::Code(ref seed) => println!("Validating seed {}...",seed),
+	}
+
+	let mut failed = 0;
+	for seed in seeds.iter() {
+		let errors = match generate_with(&seed,params,features) {
+			Ok(d) => validate(&d),
+			Err(e) => {
+				failed += 1;
+				writeln!(stderr(),"Seed {}: generation failed: {}",seed,e).ok();
+				continue;
+			}
+		};
+		if errors.len() > 0 {
+			failed += 1;
+			println!("Seed {}: {} problem(s)",seed,errors.len());
+			for err in errors.iter() {
+				println!("    {}",err);
+			}
+		}
+	}
+
+	if failed > 0 {
+		return Err(format!("{} of {} seeds failed validation.",failed,count));
+	}
+	println!("All {} seeds passed validation.",count);
+	Ok(())
+}
@@ -0,0 +1,398 @@
+// Tiled (http://www.mapeditor.org) JSON map import/export
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use rustc_serialize::json::Json;
+
+use atlas;
+use generator;
+use generator::TileType;
+use generator::{Floor, Wall, Door, Corridor, StairsUp, StairsDown, Unknown};
+use generator::DoorState::{Closed, Open, Locked, Secret};
+use generator::{Monster, Treasure, Key, Missingno};
+use gfx::SpriteRegistry;
+use world;
+
+/// Where exported maps are written; the tileset path is relative to this.
+pub static MAP_DIR: &'static str = "./res/maps/";
+static TILESET_IMAGE: &'static str = "../gfx/all_tiles.png";
+static TILE_LAYER: &'static str = "tiles";
+static OBJECT_LAYER: &'static str = "entities";
+const FIRST_GID: usize = 1;
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(io::Error),
+    Parse(String),
+    Format(String),
+}
+
+pub type TiledResult<T> = Result<T, TiledError>;
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TiledError::Io(ref e) => write!(f, "I/O error: {}", e),
+            TiledError::Parse(ref why) => write!(f, "invalid JSON: {}", why),
+            TiledError::Format(ref why) => write!(f, "invalid Tiled map: {}", why),
+        }
+    }
+}
+
+impl From<io::Error> for TiledError {
+    fn from(e: io::Error) -> Self {
+        TiledError::Io(e)
+    }
+}
+
+fn format_err<T>(why: String) -> TiledResult<T> {
+    Err(TiledError::Format(why))
+}
+
+/**********/
+/* export */
+/**********/
+
+/// Converts a dungeon into a Tiled JSON map with a tile layer and an object
+/// layer for stairs, locked and secret doors, terrain (water, traps and so
+/// on, named by a `terrain` property) and entities. Walls are left as empty
+/// cells, and so are tiles whose sprite isn't in `sprites`.
+pub fn export_dungeon(d: &generator::Dungeon, sprites: &SpriteRegistry) -> Json {
+    let data = d.get_tile_vector().iter()
+            .map(|tile| gid_for_tile_type(tile.t, sprites))
+            .collect();
+
+    let mut objects = Vec::new();
+    let (sx, sy) = d.start_coords;
+    let (ex, ey) = d.end_coords;
+    objects.push(object(1, "stairs_up", sx, sy, None));
+    objects.push(object(2, "stairs_down", ex, ey, None));
+    for tile in d.get_tile_vector().iter() {
+        let door = match tile.t {
+            Door(Locked) => Some("locked_door"),
+            Door(Secret) => Some("secret_door"),
+            _ => None,
+        };
+        if let Some(kind) = door {
+            let id = objects.len() + 1;
+            objects.push(object(id, kind, tile.x, tile.y, None));
+        }
+        if let Some(name) = d.terrain_name(tile) {
+            let id = objects.len() + 1;
+            objects.push(object(id, "terrain", tile.x, tile.y,
+                    Some(property("terrain", str_json(name)))));
+        }
+        let (kind, prop) = match tile.e {
+            Some(Monster(num)) => ("monster", Some(property("kind", Json::U64(num as u64)))),
+            Some(Treasure) => ("treasure", None),
+            Some(Key) => ("key", None),
+            Some(Missingno) | None => continue,
+        };
+        let id = objects.len() + 1;
+        objects.push(object(id, kind, tile.x, tile.y, prop));
+    }
+
+    map(d.width() as usize, d.height() as usize, data, objects)
+}
+
+/// Converts a `world` dungeon into a Tiled JSON map. Sprites are looked up
+/// from `res/gfx/tiles/<tile name>.json`; tiles without one are left empty.
+pub fn export_world(d: &world::Dungeon, sprites: &SpriteRegistry) -> Json {
+    let mut gids: HashMap<String, usize> = HashMap::new();
+    for name in d.get_map().keys() {
+        match sprites.position(name) {
+            Ok(pos) => { gids.insert(name.clone(), atlas::sprite_index(pos) + FIRST_GID); }
+            Err(e) => println!("Warning: {}", e),
+        }
+    }
+    let (w, h) = (d.get_width(), d.get_height());
+    let mut data = Vec::with_capacity(w * h);
+    for y in 0..(h as isize) {
+        for x in 0..(w as isize) {
+            let gid = d.get(x, y)
+                    .and_then(|t| gids.get(&t.name).cloned())
+                    .unwrap_or(0);
+            data.push(gid);
+        }
+    }
+    map(w, h, data, Vec::new())
+}
+
+pub fn save(map: &Json, path: &str) -> TiledResult<()> {
+    let mut f = try!(File::create(path));
+    try!(write!(f, "{}", map.pretty()));
+    Ok(())
+}
+
+fn map(w: usize, h: usize, data: Vec<usize>, objects: Vec<Json>) -> Json {
+    let tsz = atlas::TILE_SIZE;
+    let (img_w, img_h) = atlas::ATLAS_SIZE;
+    let object_count = objects.len();
+
+    let mut tiles = BTreeMap::new();
+    tiles.insert("type".to_string(), str_json("tilelayer"));
+    tiles.insert("id".to_string(), Json::U64(1));
+    tiles.insert("name".to_string(), str_json(TILE_LAYER));
+    tiles.insert("width".to_string(), Json::U64(w as u64));
+    tiles.insert("height".to_string(), Json::U64(h as u64));
+    tiles.insert("x".to_string(), Json::U64(0));
+    tiles.insert("y".to_string(), Json::U64(0));
+    tiles.insert("opacity".to_string(), Json::F64(1.0));
+    tiles.insert("visible".to_string(), Json::Boolean(true));
+    tiles.insert("data".to_string(), Json::Array(
+            data.into_iter().map(|gid| Json::U64(gid as u64)).collect()));
+
+    let mut entities = BTreeMap::new();
+    entities.insert("type".to_string(), str_json("objectgroup"));
+    entities.insert("id".to_string(), Json::U64(2));
+    entities.insert("name".to_string(), str_json(OBJECT_LAYER));
+    entities.insert("draworder".to_string(), str_json("topdown"));
+    entities.insert("x".to_string(), Json::U64(0));
+    entities.insert("y".to_string(), Json::U64(0));
+    entities.insert("opacity".to_string(), Json::F64(1.0));
+    entities.insert("visible".to_string(), Json::Boolean(true));
+    entities.insert("objects".to_string(), Json::Array(objects));
+
+    let mut tileset = BTreeMap::new();
+    tileset.insert("firstgid".to_string(), Json::U64(FIRST_GID as u64));
+    tileset.insert("name".to_string(), str_json("all_tiles"));
+    tileset.insert("image".to_string(), str_json(TILESET_IMAGE));
+    tileset.insert("imagewidth".to_string(), Json::U64(img_w as u64));
+    tileset.insert("imageheight".to_string(), Json::U64(img_h as u64));
+    tileset.insert("tilewidth".to_string(), Json::U64(tsz as u64));
+    tileset.insert("tileheight".to_string(), Json::U64(tsz as u64));
+    tileset.insert("columns".to_string(), Json::U64(atlas::ATLAS_COLUMNS as u64));
+    tileset.insert("tilecount".to_string(),
+            Json::U64(((img_w / tsz) * (img_h / tsz)) as u64));
+    tileset.insert("margin".to_string(), Json::U64(0));
+    tileset.insert("spacing".to_string(), Json::U64(0));
+
+    let mut m = BTreeMap::new();
+    m.insert("type".to_string(), str_json("map"));
+    m.insert("version".to_string(), str_json("1.10"));
+    m.insert("orientation".to_string(), str_json("orthogonal"));
+    m.insert("renderorder".to_string(), str_json("right-down"));
+    m.insert("infinite".to_string(), Json::Boolean(false));
+    m.insert("width".to_string(), Json::U64(w as u64));
+    m.insert("height".to_string(), Json::U64(h as u64));
+    m.insert("tilewidth".to_string(), Json::U64(tsz as u64));
+    m.insert("tileheight".to_string(), Json::U64(tsz as u64));
+    m.insert("nextlayerid".to_string(), Json::U64(3));
+    m.insert("nextobjectid".to_string(), Json::U64(object_count as u64 + 1));
+    m.insert("layers".to_string(), Json::Array(vec![
+        Json::Object(tiles),
+        Json::Object(entities),
+    ]));
+    m.insert("tilesets".to_string(), Json::Array(vec![Json::Object(tileset)]));
+    Json::Object(m)
+}
+
+fn object(id: usize, kind: &str, x: isize, y: isize, prop: Option<Json>) -> Json {
+    let tsz = atlas::TILE_SIZE as i64;
+    let mut o = BTreeMap::new();
+    o.insert("id".to_string(), Json::U64(id as u64));
+    o.insert("name".to_string(), str_json(""));
+    o.insert("type".to_string(), str_json(kind));
+    o.insert("x".to_string(), Json::I64(x as i64 * tsz));
+    o.insert("y".to_string(), Json::I64(y as i64 * tsz));
+    o.insert("width".to_string(), Json::I64(tsz));
+    o.insert("height".to_string(), Json::I64(tsz));
+    o.insert("rotation".to_string(), Json::F64(0.0));
+    o.insert("visible".to_string(), Json::Boolean(true));
+    if let Some(prop) = prop {
+        o.insert("properties".to_string(), Json::Array(vec![prop]));
+    }
+    Json::Object(o)
+}
+
+/// A Tiled custom property; `value` should be a string or whole number.
+fn property(name: &str, value: Json) -> Json {
+    let kind = if value.is_string() { "string" } else { "int" };
+    let mut prop = BTreeMap::new();
+    prop.insert("name".to_string(), str_json(name));
+    prop.insert("type".to_string(), str_json(kind));
+    prop.insert("value".to_string(), value);
+    Json::Object(prop)
+}
+
+fn str_json(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn gid_for_tile_type(t: TileType, sprites: &SpriteRegistry) -> usize {
+    match sprite_for_tile_type(t).and_then(|name| sprites.position(name).ok()) {
+        None => 0,
+        Some(pos) => atlas::sprite_index(pos) + FIRST_GID,
+    }
+}
+
+fn sprite_for_tile_type(t: TileType) -> Option<&'static str> {
+    match t {
+        Floor => Some("floor"),
+        Door(Open) => Some("corridor"),
+        Door(Secret) => None,
+        Door(_) => Some("door"),
+        Corridor => Some("corridor"),
+        StairsUp => Some("stairs_up"),
+        StairsDown => Some("stairs_down"),
+        Wall | Unknown => None,
+    }
+}
+
+/**********/
+/* import */
+/**********/
+
+/// Loads a map written by `export_dungeon` (and possibly hand edited in
+/// Tiled) so it can be played as a fixed level.
+pub fn load(path: &str, sprites: &SpriteRegistry) -> TiledResult<generator::Dungeon> {
+    let mut s = String::new();
+    let mut f = try!(File::open(path));
+    try!(f.read_to_string(&mut s));
+    let j = try!(Json::from_str(&s)
+            .map_err(|e| TiledError::Parse(format!("{}", e))));
+    import_dungeon(&j, sprites)
+}
+
+pub fn import_dungeon(j: &Json, sprites: &SpriteRegistry) -> TiledResult<generator::Dungeon> {
+    let w = try!(get_u64(j, "width")) as isize;
+    let h = try!(get_u64(j, "height")) as isize;
+    let tsz = try!(get_u64(j, "tilewidth")) as isize;
+    if tsz <= 0 {
+        return format_err(format!("tilewidth must be above 0, got {}", tsz));
+    }
+    let first_gid = try!(j.find("tilesets")
+            .and_then(|t| t.as_array())
+            .and_then(|t| t.get(0))
+            .ok_or(TiledError::Format("map has no tileset".into()))
+            .and_then(|t| get_u64(t, "firstgid"))) as usize;
+    let layers = try!(j.find("layers").and_then(|l| l.as_array())
+            .ok_or(TiledError::Format("map has no layers".into())));
+
+    let mut d = generator::Dungeon::empty(w, h);
+
+    let tile_layer = try!(find_layer(layers, TILE_LAYER));
+    let data = try!(tile_layer.find("data").and_then(|d| d.as_array())
+            .ok_or(TiledError::Format("tile layer has no data".into())));
+    if data.len() != (w * h) as usize {
+        return format_err(format!("expected {} tiles, found {}", w * h, data.len()));
+    }
+    let types = sprite_lookup(sprites);
+    for (i, gid) in data.iter().enumerate() {
+        let gid = try!(gid.as_u64()
+                .ok_or(TiledError::Format(format!("tile {} isn't a number", i)))) as usize;
+        d.tiles[i].t = if gid == 0 {
+            Wall
+        } else if gid < first_gid {
+            return format_err(format!("tile {} has gid {} below firstgid", i, gid));
+        } else {
+            match types.get(&atlas::sprite_pos(gid - first_gid)) {
+                Some(&t) => t,
+                None => return format_err(format!(
+                        "tile {} uses sprite {:?}, which isn't a dungeon tile",
+                        i, atlas::sprite_pos(gid - first_gid))),
+            }
+        };
+    }
+
+    let object_layer = try!(find_layer(layers, OBJECT_LAYER));
+    let objects = try!(object_layer.find("objects").and_then(|o| o.as_array())
+            .ok_or(TiledError::Format("object layer has no objects".into())));
+    let mut starts = 0;
+    let mut ends = 0;
+    for o in objects.iter() {
+        let kind = o.find("type").and_then(|t| t.as_string()).unwrap_or("");
+        // floor, so objects just off the top or left edge aren't let in
+        let x = (try!(get_f64(o, "x")) / tsz as f64).floor() as isize;
+        let y = (try!(get_f64(o, "y")) / tsz as f64).floor() as isize;
+        if x < 0 || y < 0 || x >= w || y >= h {
+            return format_err(format!("{} at ({},{}) is outside the map", kind, x, y));
+        }
+        let idx = (x + y * w) as usize;
+        match kind {
+            "stairs_up" => {
+                d.tiles[idx].t = StairsUp;
+                d.start_coords = (x, y);
+                starts += 1;
+            }
+            "stairs_down" => {
+                d.tiles[idx].t = StairsDown;
+                d.end_coords = (x, y);
+                ends += 1;
+            }
+            "locked_door" => d.tiles[idx].t = Door(Locked),
+            "secret_door" => d.tiles[idx].t = Door(Secret),
+            "monster" => d.tiles[idx].e = Some(Monster(get_kind(o))),
+            "treasure" => d.tiles[idx].e = Some(Treasure),
+            "key" => d.tiles[idx].e = Some(Key),
+            "terrain" => {
+                let name = try!(get_property(o, "terrain").and_then(|v| v.as_string())
+                        .ok_or(TiledError::Format(format!(
+                                "terrain at ({},{}) has no `terrain` name", x, y))));
+                let terrain = terrain_index(&mut d.terrain, name);
+                d.tiles[idx].terrain = Some(terrain);
+            }
+            other => return format_err(format!("unknown object type `{}` at ({},{})", other, x, y)),
+        }
+    }
+    if starts != 1 || ends != 1 {
+        return format_err(format!(
+                "map needs one stairs_up and one stairs_down object, found {} and {}",
+                starts, ends));
+    }
+
+    Ok(d)
+}
+
+fn sprite_lookup(sprites: &SpriteRegistry) -> HashMap<atlas::SpritePos, TileType> {
+    let mut types = HashMap::new();
+    // corridor comes last so open doors don't shadow it
+    for &t in [Floor, Door(Closed), Door(Open), Corridor, StairsUp, StairsDown].iter() {
+        sprite_for_tile_type(t)
+                .and_then(|name| sprites.position(name).ok())
+                .map(|pos| types.insert(pos, t));
+    }
+    types
+}
+
+fn find_layer<'a>(layers: &'a Vec<Json>, name: &str) -> TiledResult<&'a Json> {
+    layers.iter()
+            .find(|l| l.find("name").and_then(|n| n.as_string()) == Some(name))
+            .ok_or(TiledError::Format(format!("missing `{}` layer", name)))
+}
+
+fn get_u64(j: &Json, key: &str) -> TiledResult<u64> {
+    j.find(key).and_then(|v| v.as_u64())
+            .ok_or(TiledError::Format(format!("missing or invalid `{}`", key)))
+}
+
+fn get_f64(j: &Json, key: &str) -> TiledResult<f64> {
+    j.find(key).and_then(|v| v.as_f64())
+            .ok_or(TiledError::Format(format!("missing or invalid `{}`", key)))
+}
+
+fn get_property<'a>(o: &'a Json, name: &str) -> Option<&'a Json> {
+    o.find("properties")
+            .and_then(|p| p.as_array())
+            .and_then(|props| props.iter().find(|p|
+                    p.find("name").and_then(|n| n.as_string()) == Some(name)))
+            .and_then(|p| p.find("value"))
+}
+
+fn get_kind(o: &Json) -> usize {
+    get_property(o, "kind").and_then(|v| v.as_u64()).unwrap_or(0) as usize
+}
+
+// where `name` is in the dungeon's terrain names, adding it if it's new
+fn terrain_index(names: &mut Vec<String>, name: &str) -> usize {
+    match names.iter().position(|n| n == name) {
+        Some(i) => i,
+        None => {
+            names.push(name.to_string());
+            names.len() - 1
+        }
+    }
+}
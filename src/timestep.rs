@@ -0,0 +1,69 @@
+/// Simulation ticks per second unless told otherwise.
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+/// Most ticks run for one frame. A frame that's further behind than this
+/// drops the extra time rather than trying to catch up; otherwise a slow
+/// tick makes the next frame slower still, and so on.
+pub const DEFAULT_MAX_TICKS: u32 = 5;
+
+/// Turns variable frame times into a whole number of fixed-length ticks,
+/// carrying the remainder over to the next frame.
+#[derive(Clone, Debug)]
+pub struct FixedStep {
+    tick: f64,
+    max_ticks: u32,
+    acc: f64,
+    dropped: f64,
+}
+
+impl FixedStep {
+    /// `rate` is in ticks per second.
+    pub fn new(rate: f64) -> FixedStep {
+        assert!(rate > 0.0, "tick rate must be positive");
+        FixedStep {
+            tick: 1.0 / rate,
+            max_ticks: DEFAULT_MAX_TICKS,
+            acc: 0.0,
+            dropped: 0.0,
+        }
+    }
+
+    pub fn max_ticks(mut self, max_ticks: u32) -> FixedStep {
+        self.max_ticks = max_ticks;
+        self
+    }
+
+    /// Length of a tick, in seconds.
+    pub fn tick(&self) -> f64 {
+        self.tick
+    }
+
+    /// Adds a frame's worth of time and returns how many ticks to run now.
+    pub fn advance(&mut self, frame: f64) -> u32 {
+        self.acc += frame.max(0.0);
+        let ticks = (self.acc / self.tick).floor() as u32;
+        self.acc -= ticks as f64 * self.tick;
+        if ticks > self.max_ticks {
+            self.dropped += (ticks - self.max_ticks) as f64 * self.tick;
+            self.max_ticks
+        } else {
+            ticks
+        }
+    }
+
+    /// How far (0 to 1) the clock is between the last tick and the next,
+    /// for drawing things partway between where they were and are.
+    pub fn alpha(&self) -> f64 {
+        self.acc / self.tick
+    }
+
+    /// Seconds skipped by the catch-up limit so far.
+    pub fn dropped(&self) -> f64 {
+        self.dropped
+    }
+}
+
+impl Default for FixedStep {
+    fn default() -> FixedStep {
+        FixedStep::new(DEFAULT_TICK_RATE)
+    }
+}
@@ -22,6 +22,7 @@ impl AngleHelper for f32 {
 static RES_LOC: &'static str = "./res/";
 static GFX_DIR: &'static str = "gfx/";
 static SND_DIR: &'static str = "snd/";
+static FNT_DIR: &'static str = "fnt/";
 
 /* Resource management */
 
@@ -33,6 +34,10 @@ pub fn get_snd_path(fname: &str) -> String {
 	RES_LOC.to_string() + SND_DIR.into() + fname.into()
 }
 
+pub fn get_fnt_path(fname: &str) -> String {
+	RES_LOC.to_string() + FNT_DIR.into() + fname.into()
+}
+
 pub fn get_rc_resource<T>(resource : T) -> Rc<RefCell<T>> {
 	Rc::new(RefCell::new(resource))
 }
@@ -0,0 +1,151 @@
+// sanity checks for generated dungeons
+
+use std::fmt;
+use std::collections::HashSet;
+use generator::{Dungeon,TileType,Wall,Unknown,StairsUp,StairsDown,Door};
+use generator::DoorState::Secret;
+
+/// dead-end tiles allowed per passable tile before we complain
+pub static MAX_DEAD_END_RATIO: f32 = 0.05;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum ValidationError {
+	/// passable tiles that can't be reached from the up stairs, even
+	/// through secret doors
+	UnreachableRegion { first: (isize,isize), size: usize },
+	/// wrong number of up or down stairs
+	StairCount { tile: TileTypeName, count: usize },
+	/// start or end coordinates don't point at the matching stairs
+	StairMismatch { tile: TileTypeName, coords: (isize,isize) },
+	/// down stairs can't be reached from the up stairs
+	StairsUnreachable { start: (isize,isize), end: (isize,isize) },
+	/// an entity is standing on the up stairs
+	EntityOnStart { coords: (isize,isize) },
+	/// an entity is embedded in a wall
+	EntityInWall { coords: (isize,isize) },
+	/// too many corridors that go nowhere
+	DeadEnds { count: usize, ratio: f32 },
+}
+pub use self::ValidationError::*;
+
+pub type TileTypeName = &'static str;
+
+impl fmt::Display for ValidationError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			UnreachableRegion { first, size } =>
+				write!(f, "unreachable region of {} tile(s) starting at {:?}", size, first),
+			StairCount { tile, count } =>
+				write!(f, "expected exactly one {} tile, found {}", tile, count),
+			StairMismatch { tile, coords } =>
+				write!(f, "{} coordinates {:?} don't point at a {} tile", tile, coords, tile),
+			StairsUnreachable { start, end } =>
+				write!(f, "down stairs at {:?} can't be reached from up stairs at {:?}", end, start),
+			EntityOnStart { coords } =>
+				write!(f, "entity spawned on the up stairs at {:?}", coords),
+			EntityInWall { coords } =>
+				write!(f, "entity spawned inside a wall at {:?}", coords),
+			DeadEnds { count, ratio } =>
+				write!(f, "{} dead-end tile(s) ({:.1}% of floor, max {:.1}%)",
+					count, ratio * 100.0, MAX_DEAD_END_RATIO * 100.0),
+		}
+	}
+}
+
+pub fn validate(d: &Dungeon) -> Vec<ValidationError> {
+	let mut errors = Vec::new();
+
+	check_stairs(d, StairsUp, "StairsUp", d.start_coords, &mut errors);
+	check_stairs(d, StairsDown, "StairsDown", d.end_coords, &mut errors);
+
+	// entities
+	for tile in d.tiles.iter() {
+		if tile.e.is_none() { continue; }
+		let coords = (tile.x,tile.y);
+		if !is_passable(tile.t) {
+			errors.push(EntityInWall { coords: coords });
+		} else if tile.t == StairsUp {
+			errors.push(EntityOnStart { coords: coords });
+		}
+	}
+
+	// connectivity -- hidden rooms are optional, the stairs aren't
+	let reached = flood_fill(d, d.start_coords, false);
+	if !reached.contains(&d.end_coords) {
+		errors.push(StairsUnreachable { start: d.start_coords, end: d.end_coords });
+	}
+	let mut seen = flood_fill(d, d.start_coords, true);
+	for tile in d.tiles.iter() {
+		let coords = (tile.x,tile.y);
+		if !is_passable(tile.t) || seen.contains(&coords) { continue; }
+		let region = flood_fill(d, coords, true);
+		errors.push(UnreachableRegion { first: coords, size: region.len() });
+		seen.extend(region.into_iter());
+	}
+
+	// dead ends
+	let passable = d.tiles.iter().filter(|t| is_passable(t.t)).count();
+	let dead_ends = dead_end_count(d);
+	if passable > 0 {
+		let ratio = dead_ends as f32 / passable as f32;
+		if ratio > MAX_DEAD_END_RATIO {
+			errors.push(DeadEnds { count: dead_ends, ratio: ratio });
+		}
+	}
+
+	errors
+}
+
+/// number of passable tiles with exactly one passable cardinal neighbor
+pub fn dead_end_count(d: &Dungeon) -> usize {
+	d.tiles.iter()
+		.filter(|t| is_passable(t.t))
+		.filter(|t| cardinal_neighbors(d, (t.x,t.y), true).len() == 1)
+		.count()
+}
+
+pub fn is_passable(t: TileType) -> bool {
+	t != Wall && t != Unknown
+}
+
+/*************/
+/* internals */
+/*************/
+
+fn check_stairs(d: &Dungeon, t: TileType, name: TileTypeName, coords: (isize,isize),
+		errors: &mut Vec<ValidationError>) {
+	let count = d.tiles.iter().filter(|tile| tile.t == t).count();
+	if count != 1 {
+		errors.push(StairCount { tile: name, count: count });
+	}
+	let (x,y) = coords;
+	if d.get_tile_type(x,y) != Some(t) {
+		errors.push(StairMismatch { tile: name, coords: coords });
+	}
+}
+
+fn cardinal_neighbors(d: &Dungeon, coords: (isize,isize), secrets: bool) -> Vec<(isize,isize)> {
+	let (x,y) = coords;
+	[(x,y-1),(x+1,y),(x,y+1),(x-1,y)].iter()
+		.cloned()
+		.filter(|&(nx,ny)| d.get_tile_type(nx,ny)
+			.map_or(false, |t| is_passable(t) && (secrets || t != Door(Secret))))
+		.collect()
+}
+
+/// `secrets` decides whether secret doors count as found
+fn flood_fill(d: &Dungeon, start: (isize,isize), secrets: bool) -> HashSet<(isize,isize)> {
+	let mut reached = HashSet::new();
+	let (sx,sy) = start;
+	if !d.get_tile_type(sx,sy).map_or(false, is_passable) { return reached; }
+	let mut stack = vec![start];
+	reached.insert(start);
+	while let Some(coords) = stack.pop() {
+		for n in cardinal_neighbors(d, coords, secrets).into_iter() {
+			if reached.insert(n) {
+				stack.push(n);
+			}
+		}
+	}
+	reached
+}
@@ -15,6 +15,7 @@ pub struct DungeonParams {
     pub floor: String,
     pub wall: String,
     pub room_size: (usize, usize),
+    pub attempts: Option<usize>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -82,15 +83,19 @@ impl Dungeon {
         true
     }
     pub fn print(&self) {
+        print!("{}", self.to_ascii());
+        println!("Size: {}x{}", self.get_width(), self.get_height());
+    }
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::new();
         for y in 0..(self.get_height() as isize) {
             for x in 0..(self.get_width() as isize) {
                 let t = self.get(x, y).unwrap();
-                let c = if t.wall { '*' } else { ' ' };
-                print!("{}", c);
+                out.push(if t.wall { '*' } else { ' ' });
             }
-            println!("");
+            out.push('\n');
         }
-        println!("Size: {}x{}", self.get_width(), self.get_height());
+        out
     }
     /* getters */
     pub fn get_width(&self) -> usize {
@@ -4,29 +4,43 @@ use num::traits::One;
 use num::integer::Integer;
 use std::ops::Add;
 use std::collections::HashMap;
-use rand::{Rng, SeedableRng, XorShiftRng};
+use rand::Rng;
+use gen_error::*;
+use seed::{Seed, Stream};
 use super::tiles::*;
 use super::dungeon::*;
 
+/// Room placements to try when `DungeonParams::attempts` isn't set.
+pub const DEFAULT_ATTEMPTS: usize = 10000;
+
 // pub fn generate_default() -> Dungeon {
 //     generate(Default::default())
 // }
 
-pub fn generate(params: DungeonParams, map: TileMap) -> Dungeon {
+pub fn generate(params: DungeonParams, map: TileMap) -> GenResult<Dungeon> {
+    let floor = try!(map.get(&params.floor).cloned()
+            .ok_or(UnknownTile(params.floor.clone())));
+    try!(map.get(&params.wall).cloned()
+            .ok_or(UnknownTile(params.wall.clone())));
+    try!(check_params(&params));
     let mut d = Dungeon::empty(params, map);
     let p = d.get_params().clone();
-    let m = d.get_map().clone();
-    let floor = m.get(&p.floor)
-            .expect("Error: floor tile could not be found");
-    let wall = m.get(&p.wall)
-            .expect("Error: wall tile could not be found");
-    let seed = [p.seed, p.seed/4+999, p.seed/2+3, p.seed/3+1337];
-    let mut rng = XorShiftRng::from_seed(seed);
+    let mut rng = Seed::from(p.seed).rng(0, Stream::Layout);
     let rng = &mut rng;
     let total_rooms = int_range(rng, p.rooms); // rng.gen_range(p.rooms.0, p.rooms.1 + 1);
     println!("total_rooms={}", total_rooms);
+    let max_attempts = p.attempts.unwrap_or(DEFAULT_ATTEMPTS);
+    let mut attempts = 0;
     let mut rooms = Vec::new();
     while rooms.len() < total_rooms {
+        if attempts >= max_attempts {
+            return Err(RetryBudgetExhausted {
+                placed: rooms.len(),
+                wanted: total_rooms,
+                attempts: attempts,
+            });
+        }
+        attempts += 1;
         let room = {
             let w = int_range(rng, p.room_size);
             let h = int_range(rng, p.room_size);
@@ -34,12 +48,39 @@ pub fn generate(params: DungeonParams, map: TileMap) -> Dungeon {
             let y = int_range( rng, (0, d.get_height() - h - 1) );
             Room {  w: w, h: h, x: x as isize, y: y as isize }
         };
-        if d.fill_room(room, floor) {
+        if d.fill_room(room, &floor) {
             rooms.push(room);
             println!("...{}", rooms.len());
         }
     }
-    d
+    Ok(d)
+}
+
+/// Rejects parameters that could never produce a dungeon.
+fn check_params(p: &DungeonParams) -> GenResult<()> {
+    let (min_rooms, max_rooms) = p.rooms;
+    let (min_size, max_size) = p.room_size;
+    let (w, h) = p.size;
+    if min_rooms > max_rooms {
+        return Err(ImpossibleParams(
+                format!("room count range {:?} is backwards", p.rooms)));
+    }
+    if min_size == 0 || min_size > max_size {
+        return Err(ImpossibleParams(
+                format!("bad room size range {:?}", p.room_size)));
+    }
+    // rooms need a wall on every side
+    if w < max_size + 2 || h < max_size + 2 {
+        return Err(ImpossibleParams(
+                format!("{}x{} map can't fit a {}-tile room", w, h, max_size)));
+    }
+    let room_area = (min_size + 1) * (min_size + 1);
+    if min_rooms * room_area > (w - 1) * (h - 1) {
+        return Err(ImpossibleParams(
+                format!("{} rooms of at least {}x{} can't fit in a {}x{} map",
+                        min_rooms, min_size, min_size, w, h)));
+    }
+    Ok(())
 }
 
 /// inclusive
@@ -47,4 +88,4 @@ fn int_range<R, T>(rng: &mut R, range: (T, T)) -> T
         where R: Rng, T: PartialOrd + SampleRange + One + Integer
 {
     rng.gen_range(range.0, range.1 + T::one())
-}
\ No newline at end of file
+}
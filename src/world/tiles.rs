@@ -1,19 +1,63 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::rc::Rc;
+use rustc_serialize::json;
 // use petgraph::?;
 use utils::*;
 use poglgame::Texture;
 use poglgame::Sprite;
 
-#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq, Eq)]
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
 pub struct TileInfo {
     pub name: String,
     pub wall: bool,
     pub opaque: bool,
+    /// movement cost multiplier; 1.0 when missing
+    pub cost: Option<f32>,
+    pub hazard: Option<HazardInfo>,
+    /// first floor the generator scatters it on; never, if missing
+    pub min_floor: Option<u32>,
+    /// color multiplied into the floor under it
+    pub tint: Option<(u8, u8, u8)>,
+}
+
+/// Something on a tile that hurts whoever steps on it.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct HazardInfo {
+    /// damage dealt when stepping onto the tile
+    pub damage: isize,
+    /// if set, the damage repeats every `interval` seconds while standing on it
+    pub interval: Option<f32>,
+    /// unseen until searched for or stepped on
+    pub hidden: bool,
+    /// fires arrows along the tile's row and column, from wall to wall;
+    /// `damage` goes to everyone in their way instead of whoever stepped on it
+    pub arrows: bool,
+    /// extra path cost multiplier once the hazard is known
+    pub avoid: f32,
+}
+
+impl TileInfo {
+    pub fn move_cost(&self) -> f32 {
+        self.cost.unwrap_or(1.0)
+    }
 }
 
 pub type TileMap = HashMap<String, TileInfo>;
 
+pub static TILES_FILE: &'static str = "./res/dat/tiles.json";
+
+/// Reads a JSON list of tiles into a map keyed by name.
+pub fn load_tile_map(path: &str) -> Result<TileMap, String> {
+    let mut tile_str = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut tile_str))
+            .map_err(|e| format!("{}: {}", path, e)));
+    let tile_vec: Vec<TileInfo> = try!(json::decode(&tile_str)
+            .map_err(|e| format!("{}: {}", path, e)));
+    Ok(tile_vec.into_iter().map(|t| (t.name.clone(), t)).collect())
+}
+
 #[derive(Clone)]
 pub struct Tile {
     pub info: TileInfo,
@@ -26,6 +70,10 @@ impl Default for TileInfo {
             name: "".into(),
             wall: true,
             opaque: true,
+            cost: None,
+            hazard: None,
+            min_floor: None,
+            tint: None,
         }
     }
 }
\ No newline at end of file